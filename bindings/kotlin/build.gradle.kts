@@ -0,0 +1,24 @@
+// Kotlin wrapper over the JNI exports of libbw_mpc_client.so, built with
+// `cargo build --release --features jni --target <android target>`; copy the
+// libraries to src/main/jniLibs/<abi>/ of the app.
+plugins {
+    kotlin("jvm") version "1.9.22"
+}
+
+group = "zone.bitverse"
+version = "0.1.0"
+
+repositories {
+    mavenCentral()
+}
+
+dependencies {
+    // bundled with Android, only needed to compile and test on the JVM
+    compileOnly("org.json:json:20231013")
+    testImplementation("org.json:json:20231013")
+    testImplementation(kotlin("test"))
+}
+
+tasks.test {
+    useJUnitPlatform()
+}
@@ -0,0 +1,78 @@
+package zone.bitverse.mpc
+
+import org.json.JSONObject
+import org.json.JSONTokener
+
+/**
+ * Two-party ECDSA client backed by libbw_mpc_client.
+ *
+ * Every call blocks on the network or on curve arithmetic; run it off the
+ * main thread. Failures are thrown as [MpcException].
+ */
+object MpcClient {
+    /** ABI version of the C functions these bindings were written against. */
+    const val ABI_VERSION = 1
+
+    init {
+        System.loadLibrary("bw_mpc_client")
+    }
+
+    /** Whether the loaded library speaks the ABI of these bindings. */
+    val isCompatible: Boolean get() = nativeAbiVersion() == ABI_VERSION
+
+    fun version(): Version = Version.fromJson(result(nativeVersion()) as JSONObject)
+
+    /** Runs a keygen with the party one server at [endpoint]. */
+    fun keygen(endpoint: String, authToken: String): PrivateShare =
+        PrivateShare.fromJson(result(nativeKeygen(endpoint, authToken)) as JSONObject)
+
+    fun derive(masterKeyJson: String, path: String): DerivedKey =
+        DerivedKey.fromJson(result(nativeDerive(masterKeyJson, path)) as JSONObject)
+
+    /** Compressed public key of the child at [path], as hex. */
+    fun publicKey(masterKeyJson: String, path: String): String =
+        result(nativePublicKey(masterKeyJson, path)) as String
+
+    /**
+     * Signs [messageHex], the hex of the 32 byte hash, with the child of
+     * [share] at [path].
+     */
+    fun sign(
+        endpoint: String,
+        authToken: String,
+        messageHex: String,
+        share: PrivateShare,
+        path: String,
+    ): Signature {
+        // the library takes the message as a JSON string
+        val message = JSONObject.quote(messageHex.removePrefix("0x"))
+        val cResult = nativeSign(endpoint, authToken, message, share.masterKeyJson, path, share.id)
+        return Signature.fromJson(result(cResult) as JSONObject)
+    }
+
+    /** Parses a `CResult`, returning its decoded `result` or throwing. */
+    private fun result(cResult: String?): Any {
+        if (cResult == null) {
+            throw MpcException.System(MpcException.SYSTEM, "no result from the library")
+        }
+        val json = JSONObject(cResult)
+        if (json.getInt("ret_code") != 0) {
+            throw MpcException.fromCResult(json)
+        }
+        return JSONTokener(json.getString("result")).nextValue()
+    }
+
+    @JvmStatic private external fun nativeAbiVersion(): Int
+    @JvmStatic private external fun nativeVersion(): String?
+    @JvmStatic private external fun nativeKeygen(endpoint: String, authToken: String): String?
+    @JvmStatic private external fun nativeDerive(masterKeyJson: String, path: String): String?
+    @JvmStatic private external fun nativePublicKey(masterKeyJson: String, path: String): String?
+    @JvmStatic private external fun nativeSign(
+        endpoint: String,
+        authToken: String,
+        messageHex: String,
+        masterKeyJson: String,
+        path: String,
+        id: String,
+    ): String?
+}
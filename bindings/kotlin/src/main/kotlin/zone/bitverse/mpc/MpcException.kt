@@ -0,0 +1,60 @@
+package zone.bitverse.mpc
+
+import org.json.JSONObject
+
+/**
+ * A failed call, mapped from the `ret_code` of the library's `CResult`.
+ * The codes are listed in the README of bw-mpc-lib-client.
+ */
+sealed class MpcException(val code: Int, message: String) : Exception(message) {
+    /** Whether repeating the same call can reasonably succeed. */
+    open val isRetryable: Boolean get() = false
+
+    class Network(message: String) : MpcException(NETWORK, message) {
+        override val isRetryable get() = true
+    }
+    class ServerRejected(message: String) : MpcException(SERVER_REJECTED, message)
+    class MalformedResponse(message: String) : MpcException(MALFORMED_RESPONSE, message)
+    class ProofVerification(message: String) : MpcException(PROOF_VERIFICATION, message)
+    class InvalidInput(message: String) : MpcException(INVALID_INPUT, message)
+    class KeyFormat(message: String) : MpcException(KEY_FORMAT, message)
+    /** [status] is null only with a library that does not report it. */
+    class HttpStatus(val status: Int?, message: String) : MpcException(HTTP_STATUS, message) {
+        override val isRetryable get() = status != null && (status >= 500 || status == 429)
+    }
+    class InvalidSignature(message: String) : MpcException(INVALID_SIGNATURE, message)
+    class Decryption(message: String) : MpcException(DECRYPTION, message)
+    class System(code: Int, message: String) : MpcException(code, message)
+
+    companion object {
+        const val SYSTEM = 10104000
+        const val NETWORK = 10104001
+        const val SERVER_REJECTED = 10104002
+        const val MALFORMED_RESPONSE = 10104003
+        const val PROOF_VERIFICATION = 10104004
+        const val INVALID_INPUT = 10104005
+        const val KEY_FORMAT = 10104006
+        const val HTTP_STATUS = 10104007
+        const val INVALID_SIGNATURE = 10104008
+        const val DECRYPTION = 10104009
+
+        fun of(code: Int, message: String, httpStatus: Int? = null): MpcException = when (code) {
+            NETWORK -> Network(message)
+            SERVER_REJECTED -> ServerRejected(message)
+            MALFORMED_RESPONSE -> MalformedResponse(message)
+            PROOF_VERIFICATION -> ProofVerification(message)
+            INVALID_INPUT -> InvalidInput(message)
+            KEY_FORMAT -> KeyFormat(message)
+            HTTP_STATUS -> HttpStatus(httpStatus, message)
+            INVALID_SIGNATURE -> InvalidSignature(message)
+            DECRYPTION -> Decryption(message)
+            else -> System(code, message)
+        }
+
+        /** The exception of a failed `CResult`, whose `ret_code` is not 0. */
+        internal fun fromCResult(json: JSONObject): MpcException {
+            val httpStatus = if (json.has("http_status")) json.getInt("http_status") else null
+            return of(json.getInt("ret_code"), json.optString("ret_msg"), httpStatus)
+        }
+    }
+}
@@ -0,0 +1,37 @@
+package zone.bitverse.mpc
+
+import org.json.JSONObject
+
+/**
+ * A party-two key share. [masterKeyJson] is opaque and is passed back as is;
+ * it is secret and must be stored encrypted.
+ */
+data class PrivateShare(val id: String, val masterKeyJson: String) {
+    companion object {
+        internal fun fromJson(json: JSONObject) =
+            PrivateShare(json.getString("id"), json.getJSONObject("master_key").toString())
+    }
+}
+
+/** The child master key at [path], e.g. `m/44/60/0/0/0`. */
+data class DerivedKey(val path: String, val masterKeyJson: String) {
+    companion object {
+        internal fun fromJson(json: JSONObject) =
+            DerivedKey(json.getString("path"), json.getJSONObject("master_key").toString())
+    }
+}
+
+/** ECDSA signature with low `s`; [r] and [s] are hex. */
+data class Signature(val r: String, val s: String, val recid: Int) {
+    companion object {
+        internal fun fromJson(json: JSONObject) =
+            Signature(json.getString("r"), json.getString("s"), json.getInt("recid"))
+    }
+}
+
+data class Version(val version: String, val abiVersion: Int) {
+    companion object {
+        internal fun fromJson(json: JSONObject) =
+            Version(json.getString("version"), json.getInt("abi_version"))
+    }
+}
@@ -0,0 +1,46 @@
+package zone.bitverse.mpc
+
+import org.json.JSONObject
+import kotlin.test.Test
+import kotlin.test.assertEquals
+import kotlin.test.assertFalse
+import kotlin.test.assertIs
+import kotlin.test.assertNull
+import kotlin.test.assertTrue
+
+class MpcExceptionTest {
+    @Test
+    fun errorCodes() {
+        assertIs<MpcException.Network>(MpcException.of(10104001, "down"))
+        assertIs<MpcException.InvalidInput>(MpcException.of(10104005, "bad path"))
+        assertIs<MpcException.Decryption>(MpcException.of(10104009, "wrong password"))
+        assertEquals(10104000, assertIs<MpcException.System>(MpcException.of(10104000, "panic")).code)
+        assertEquals(42, assertIs<MpcException.System>(MpcException.of(42, "?")).code)
+        assertTrue(MpcException.of(10104001, "").isRetryable)
+        assertFalse(MpcException.of(10104002, "").isRetryable)
+    }
+
+    @Test
+    fun httpStatusRetry() {
+        assertTrue(MpcException.of(10104007, "", 503).isRetryable)
+        assertTrue(MpcException.of(10104007, "", 429).isRetryable)
+        assertFalse(MpcException.of(10104007, "", 403).isRetryable)
+        assertFalse(MpcException.of(10104007, "").isRetryable)
+    }
+
+    @Test
+    fun errorReply() {
+        // as written by error_to_c_string
+        val reply = JSONObject("""{"ret_code":10104007,"ret_msg":"Error: http status 502: bad gateway","result":"","http_status":502}""")
+        val e = assertIs<MpcException.HttpStatus>(MpcException.fromCResult(reply))
+        assertEquals(502, e.status)
+        assertEquals("Error: http status 502: bad gateway", e.message)
+        assertTrue(e.isRetryable)
+
+        val invalid = JSONObject("""{"ret_code":10104005,"ret_msg":"Error: invalid input: decoding raw path failed","result":""}""")
+        val input = assertIs<MpcException.InvalidInput>(MpcException.fromCResult(invalid))
+        assertEquals(10104005, input.code)
+        assertFalse(input.isRetryable)
+        assertNull((MpcException.fromCResult(JSONObject("""{"ret_code":10104007,"ret_msg":""}""")) as MpcException.HttpStatus).status)
+    }
+}
@@ -0,0 +1,17 @@
+// swift-tools-version:5.5
+// Swift wrapper over the C functions of libbw_mpc_client. Build
+// BwMpcClientFFI.xcframework first with ./build-xcframework.sh.
+import PackageDescription
+
+let package = Package(
+    name: "BwMpcClient",
+    platforms: [.iOS(.v13), .macOS(.v10_15)],
+    products: [
+        .library(name: "BwMpcClient", targets: ["BwMpcClient"]),
+    ],
+    targets: [
+        .binaryTarget(name: "BwMpcClientFFI", path: "BwMpcClientFFI.xcframework"),
+        .target(name: "BwMpcClient", dependencies: ["BwMpcClientFFI"]),
+        .testTarget(name: "BwMpcClientTests", dependencies: ["BwMpcClient"]),
+    ]
+)
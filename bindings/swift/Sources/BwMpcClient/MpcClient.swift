@@ -0,0 +1,91 @@
+import BwMpcClientFFI
+import Foundation
+
+/// Two-party ECDSA client backed by libbw_mpc_client.
+///
+/// `keygen` and `sign` talk to the party one server and run off the calling
+/// thread; the other functions are local. Failures are thrown as `MpcError`.
+public enum MpcClient {
+    /// ABI version of the C functions these bindings were written against.
+    public static let abiVersion: UInt32 = UInt32(MPC_CLIENT_ABI_VERSION)
+
+    /// Whether the loaded library speaks the ABI of these bindings.
+    public static var isCompatible: Bool {
+        mpc_client_abi_version() == abiVersion
+    }
+
+    public static func version() throws -> Version {
+        try decode(Version.self, call { mpc_client_version() })
+    }
+
+    /// Runs a keygen with the party one server at `endpoint`.
+    public static func keygen(endpoint: String, authToken: String) async throws -> PrivateShare {
+        try await background {
+            try PrivateShare(json: call { get_client_master_key(endpoint, authToken) })
+        }
+    }
+
+    public static func derive(masterKeyJSON: String, path: String) throws -> DerivedKey {
+        let object = try jsonObject(call { key_derive_path(masterKeyJSON, path) })
+        guard let path = object["path"] as? String, let masterKey = object["master_key"] else {
+            throw MpcError.malformedResponse("not a derived key")
+        }
+        return DerivedKey(path: path, masterKeyJSON: try jsonString(masterKey))
+    }
+
+    /// Compressed public key of the child at `path`, as hex.
+    public static func publicKey(masterKeyJSON: String, path: String) throws -> String {
+        let json = try call { get_public_share_key_with_path(masterKeyJSON, path) }
+        guard let key = try? JSONSerialization.jsonObject(with: Data(json.utf8), options: [.fragmentsAllowed]) as? String else {
+            throw MpcError.malformedResponse("not a public key")
+        }
+        return key
+    }
+
+    /// Signs `messageHex`, the hex of the 32 byte hash, with the child of
+    /// `share` at `path`.
+    public static func sign(
+        endpoint: String,
+        authToken: String,
+        messageHex: String,
+        share: PrivateShare,
+        path: String
+    ) async throws -> Signature {
+        // the library takes the message as a JSON string
+        let message = try jsonString(messageHex.hasPrefix("0x") ? String(messageHex.dropFirst(2)) : messageHex)
+        let json = try await background {
+            try call { sign_message_with_path(endpoint, authToken, message, share.masterKeyJSON, path, share.id) }
+        }
+        return try decode(Signature.self, json)
+    }
+
+    /// Runs a C function, frees its `CResult` and returns the `result` JSON.
+    static func call(_ f: () -> UnsafeMutablePointer<CChar>?) throws -> String {
+        guard let pointer = f() else {
+            throw MpcError.system(code: 10104000, message: "no result from the library")
+        }
+        defer { free_char(pointer) }
+
+        let cResult = try JSONDecoder().decode(CResult.self, from: Data(String(cString: pointer).utf8))
+        guard cResult.ret_code == 0 else {
+            throw MpcError(code: cResult.ret_code, message: cResult.ret_msg, httpStatus: cResult.http_status)
+        }
+        return cResult.result
+    }
+
+    static func decode<T: Decodable>(_ type: T.Type, _ json: String) throws -> T {
+        do {
+            return try JSONDecoder().decode(type, from: Data(json.utf8))
+        } catch {
+            throw MpcError.malformedResponse("\(error)")
+        }
+    }
+
+    static func background<T>(_ work: @escaping () throws -> T) async throws -> T {
+        try await withCheckedThrowingContinuation { continuation in
+            DispatchQueue.global(qos: .userInitiated).async {
+                continuation.resume(with: Result { try work() })
+            }
+        }
+    }
+}
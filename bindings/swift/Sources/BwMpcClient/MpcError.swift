@@ -0,0 +1,42 @@
+/// A failed call, mapped from the `ret_code` of the library's `CResult`.
+/// The codes are listed in the README of bw-mpc-lib-client.
+public enum MpcError: Error, Equatable {
+    case network(String)
+    case serverRejected(String)
+    case malformedResponse(String)
+    case proofVerification(String)
+    case invalidInput(String)
+    case keyFormat(String)
+    /// `status` is nil only with a library that does not report it.
+    case httpStatus(status: UInt16?, message: String)
+    case invalidSignature(String)
+    case decryption(String)
+    case system(code: Int32, message: String)
+
+    init(code: Int32, message: String, httpStatus: UInt16? = nil) {
+        switch code {
+        case 10104001: self = .network(message)
+        case 10104002: self = .serverRejected(message)
+        case 10104003: self = .malformedResponse(message)
+        case 10104004: self = .proofVerification(message)
+        case 10104005: self = .invalidInput(message)
+        case 10104006: self = .keyFormat(message)
+        case 10104007: self = .httpStatus(status: httpStatus, message: message)
+        case 10104008: self = .invalidSignature(message)
+        case 10104009: self = .decryption(message)
+        default: self = .system(code: code, message: message)
+        }
+    }
+
+    /// Whether repeating the same call can reasonably succeed.
+    public var isRetryable: Bool {
+        switch self {
+        case .network:
+            return true
+        case .httpStatus(let status?, _):
+            return status >= 500 || status == 429
+        default:
+            return false
+        }
+    }
+}
@@ -0,0 +1,70 @@
+import Foundation
+
+/// A party-two key share. `masterKeyJSON` is opaque and is passed back as
+/// is; it is secret and must be kept in the keychain or sealed.
+public struct PrivateShare: Equatable {
+    public let id: String
+    public let masterKeyJSON: String
+
+    public init(id: String, masterKeyJSON: String) {
+        self.id = id
+        self.masterKeyJSON = masterKeyJSON
+    }
+
+    /// The `PrivateShare` JSON of the C functions.
+    public init(json: String) throws {
+        let object = try jsonObject(json)
+        guard let id = object["id"] as? String, let masterKey = object["master_key"] else {
+            throw MpcError.keyFormat("not a PrivateShare")
+        }
+        self.init(id: id, masterKeyJSON: try jsonString(masterKey))
+    }
+
+    public func json() throws -> String {
+        let masterKey = try JSONSerialization.jsonObject(with: Data(masterKeyJSON.utf8))
+        return try jsonString(["id": id, "master_key": masterKey])
+    }
+}
+
+/// The child master key at `path`, e.g. `m/44/60/0/0/0`.
+public struct DerivedKey: Equatable {
+    public let path: String
+    public let masterKeyJSON: String
+}
+
+/// ECDSA signature with low `s`; `r` and `s` are hex.
+public struct Signature: Codable, Equatable {
+    public let r: String
+    public let s: String
+    public let recid: UInt8
+}
+
+public struct Version: Codable, Equatable {
+    public let version: String
+    public let abiVersion: UInt32
+
+    enum CodingKeys: String, CodingKey {
+        case version
+        case abiVersion = "abi_version"
+    }
+}
+
+struct CResult: Decodable {
+    let ret_code: Int32
+    let ret_msg: String
+    let result: String
+    /// Only with error code 10104007.
+    let http_status: UInt16?
+}
+
+func jsonObject(_ json: String) throws -> [String: Any] {
+    guard let object = try JSONSerialization.jsonObject(with: Data(json.utf8)) as? [String: Any] else {
+        throw MpcError.malformedResponse("expected a JSON object")
+    }
+    return object
+}
+
+func jsonString(_ object: Any) throws -> String {
+    let data = try JSONSerialization.data(withJSONObject: object, options: [.fragmentsAllowed])
+    return String(decoding: data, as: UTF8.self)
+}
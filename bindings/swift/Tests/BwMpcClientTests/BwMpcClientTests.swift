@@ -0,0 +1,42 @@
+import XCTest
+@testable import BwMpcClient
+
+final class BwMpcClientTests: XCTestCase {
+    func testErrorCodes() {
+        XCTAssertEqual(MpcError(code: 10104001, message: "down"), .network("down"))
+        XCTAssertEqual(MpcError(code: 10104005, message: "bad path"), .invalidInput("bad path"))
+        XCTAssertEqual(MpcError(code: 10104009, message: "wrong password"), .decryption("wrong password"))
+        XCTAssertEqual(MpcError(code: 10104000, message: "panic"), .system(code: 10104000, message: "panic"))
+        XCTAssertEqual(MpcError(code: 42, message: "?"), .system(code: 42, message: "?"))
+        XCTAssertTrue(MpcError(code: 10104001, message: "").isRetryable)
+        XCTAssertFalse(MpcError(code: 10104002, message: "").isRetryable)
+    }
+
+    func testHttpStatusRetry() {
+        XCTAssertEqual(MpcError(code: 10104007, message: "busy", httpStatus: 503), .httpStatus(status: 503, message: "busy"))
+        XCTAssertTrue(MpcError(code: 10104007, message: "", httpStatus: 503).isRetryable)
+        XCTAssertTrue(MpcError(code: 10104007, message: "", httpStatus: 429).isRetryable)
+        XCTAssertFalse(MpcError(code: 10104007, message: "", httpStatus: 403).isRetryable)
+        XCTAssertFalse(MpcError(code: 10104007, message: "").isRetryable)
+    }
+
+    func testErrorReply() throws {
+        // as written by error_to_c_string
+        let reply = #"{"ret_code":10104005,"ret_msg":"Error: invalid input: decoding raw path failed","result":""}"#
+        let cResult = try JSONDecoder().decode(CResult.self, from: Data(reply.utf8))
+        XCTAssertEqual(cResult.ret_code, 10104005)
+        XCTAssertEqual(cResult.result, "")
+        XCTAssertNil(cResult.http_status)
+        XCTAssertEqual(
+            MpcError(code: cResult.ret_code, message: cResult.ret_msg),
+            .invalidInput("Error: invalid input: decoding raw path failed")
+        )
+
+        let http = #"{"ret_code":10104007,"ret_msg":"Error: http status 502: bad gateway","result":"","http_status":502}"#
+        let httpResult = try JSONDecoder().decode(CResult.self, from: Data(http.utf8))
+        XCTAssertEqual(
+            MpcError(code: httpResult.ret_code, message: httpResult.ret_msg, httpStatus: httpResult.http_status),
+            .httpStatus(status: 502, message: "Error: http status 502: bad gateway")
+        )
+    }
+}
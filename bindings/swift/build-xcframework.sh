@@ -0,0 +1,34 @@
+#!/bin/sh
+# Builds BwMpcClientFFI.xcframework from the static library for devices,
+# simulators and macOS (for `swift test`), with include/bw_mpc_client.h and
+# the module map as headers.
+set -e
+
+cd "$(dirname "$0")"
+ROOT=../..
+HEADERS=build/headers
+
+cargo build --manifest-path $ROOT/Cargo.toml --release --target aarch64-apple-ios
+cargo build --manifest-path $ROOT/Cargo.toml --release --target aarch64-apple-ios-sim
+cargo build --manifest-path $ROOT/Cargo.toml --release --target x86_64-apple-ios
+cargo build --manifest-path $ROOT/Cargo.toml --release --target aarch64-apple-darwin
+cargo build --manifest-path $ROOT/Cargo.toml --release --target x86_64-apple-darwin
+
+rm -rf build BwMpcClientFFI.xcframework
+mkdir -p $HEADERS build/ios-simulator build/macos
+cp $ROOT/include/bw_mpc_client.h include/module.modulemap $HEADERS/
+
+lipo -create \
+  $ROOT/target/aarch64-apple-ios-sim/release/libbw_mpc_client.a \
+  $ROOT/target/x86_64-apple-ios/release/libbw_mpc_client.a \
+  -output build/ios-simulator/libbw_mpc_client.a
+lipo -create \
+  $ROOT/target/aarch64-apple-darwin/release/libbw_mpc_client.a \
+  $ROOT/target/x86_64-apple-darwin/release/libbw_mpc_client.a \
+  -output build/macos/libbw_mpc_client.a
+
+xcodebuild -create-xcframework \
+  -library $ROOT/target/aarch64-apple-ios/release/libbw_mpc_client.a -headers $HEADERS \
+  -library build/ios-simulator/libbw_mpc_client.a -headers $HEADERS \
+  -library build/macos/libbw_mpc_client.a -headers $HEADERS \
+  -output BwMpcClientFFI.xcframework
@@ -0,0 +1,97 @@
+// Regenerates testAssets/derive_vectors.json, the known answers for child key
+// derivation checked by `test_derive_vectors` and `test_public_share_key`:
+//
+//     cargo run --example derive_vectors > testAssets/derive_vectors.json
+//
+// Only regenerate when a change to derivation is intended; the diff of the
+// fixture is the review of that change.
+#[macro_use]
+extern crate serde_derive;
+
+use curv::elliptic::curves::secp256_k1::GE;
+use curv::elliptic::curves::traits::ECPoint;
+use curv::BigInt;
+use kms::ecdsa::two_party::{MasterKey2, Party2Public};
+
+use bw_mpc_client::ecdsa::path::DerivationPath;
+use bw_mpc_client::ecdsa::PrivateShare;
+use bw_mpc_client::Converter;
+
+const MASTER_KEY: &str = "testAssets/mk.json";
+const PARTY2_PUBLIC: &str = "testAssets/party2public.json";
+
+const PATHS: &[&str] = &[
+    "m/0", "m/1", "m/60",
+    "m/0/0", "m/0/1", "m/60/0", "m/60/1", "m/60/2", "m/195/0", "m/714/0",
+    "m/44/60", "m/44/60/0",
+    "m/44/60/0/0/0", "m/44/60/0/0/1", "m/44/60/0/0/5",
+    "m/44/0/0/0/0", "m/49/0/0/0/0", "m/84/0/0/0/0", "m/44/195/0/0/0",
+    "m/2147483647", "m/2147483648", "m/4294967295",
+    "m/1/2/3/4/5/6/7/8/9/10",
+];
+
+#[derive(Serialize)]
+struct Vectors {
+    master_key: &'static str,
+    public: Key,
+    party2_public: PublicShare,
+    vectors: Vec<Vector>,
+}
+
+#[derive(Serialize)]
+struct Key {
+    q: String,
+    p2: String,
+    chain_code: String,
+}
+
+#[derive(Serialize)]
+struct PublicShare {
+    file: &'static str,
+    q: String,
+}
+
+#[derive(Serialize)]
+struct Vector {
+    path: String,
+    #[serde(flatten)]
+    key: Key,
+}
+
+fn main() {
+    let private_share: PrivateShare = serde_json::from_str(&std::fs::read_to_string(MASTER_KEY).unwrap()).unwrap();
+    let party2_public: Party2Public = serde_json::from_str(&std::fs::read_to_string(PARTY2_PUBLIC).unwrap()).unwrap();
+    let mk = &private_share.master_key;
+
+    let vectors = PATHS.iter().map(|path| {
+        let path: DerivationPath = path.parse().unwrap();
+        Vector {
+            path: path.to_string(),
+            key: key(&mk.get_child(path.to_bigints())),
+        }
+    }).collect();
+
+    let out = Vectors {
+        master_key: MASTER_KEY,
+        public: key(mk),
+        party2_public: PublicShare { file: PARTY2_PUBLIC, q: compressed_hex(&party2_public.q) },
+        vectors,
+    };
+    println!("{}", serde_json::to_string_pretty(&out).unwrap());
+}
+
+fn key(mk: &MasterKey2) -> Key {
+    Key {
+        q: compressed_hex(&mk.public.q),
+        p2: compressed_hex(&mk.public.p2),
+        chain_code: fixed_hex(&mk.chain_code),
+    }
+}
+
+fn compressed_hex(q: &GE) -> String {
+    fixed_hex(&q.bytes_compressed_to_big_int())
+}
+
+fn fixed_hex(n: &BigInt) -> String {
+    format!("{:0>66}", n.to_hex())
+}
@@ -0,0 +1,189 @@
+/* Generated by cbindgen from src/, do not edit: cbindgen -c cbindgen.toml -o include/bw_mpc_client.h */
+
+#ifndef BW_MPC_CLIENT_H
+#define BW_MPC_CLIENT_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define DECRYPTION_CODE 10104009
+
+#define HTTP_STATUS_CODE 10104007
+
+#define INVALID_INPUT_CODE 10104005
+
+#define INVALID_SIGNATURE_CODE 10104008
+
+#define KEY_FORMAT_CODE 10104006
+
+#define MALFORMED_RESPONSE_CODE 10104003
+
+#define MPC_CLIENT_ABI_VERSION 1
+
+#define NETWORK_ERROR_CODE 10104001
+
+#define PROOF_VERIFICATION_CODE 10104004
+
+#define SERVER_REJECTED_CODE 10104002
+
+#define SUCCESS_CODE 0
+
+#define SYSTEM_ERROR_CODE 10104000
+
+typedef struct ClientShim ClientShim;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+char *btc_get_address(const char *c_master_key_json,
+                      const char *c_path,
+                      const char *c_address_type,
+                      const char *c_network);
+
+char *btc_sign_psbt(const char *c_endpoint,
+                    const char *c_auth_token,
+                    const char *c_psbt,
+                    const char *c_master_key_json,
+                    const char *c_path,
+                    const char *c_id,
+                    bool c_allow_any_sighash);
+
+char *decode_signature(const char *c_signature_hex, const char *c_format);
+
+char *encode_signature(const char *c_signature_json, const char *c_format);
+
+char *eth_get_address(const char *c_master_key_json, const char *c_path);
+
+char *eth_personal_sign(const char *c_endpoint,
+                        const char *c_auth_token,
+                        const char *c_message,
+                        const char *c_master_key_json,
+                        const char *c_path,
+                        const char *c_id);
+
+char *eth_sign_transaction(const char *c_endpoint,
+                           const char *c_auth_token,
+                           const char *c_transaction_json,
+                           const char *c_master_key_json,
+                           const char *c_path,
+                           const char *c_id);
+
+char *eth_sign_typed_data(const char *c_endpoint,
+                          const char *c_auth_token,
+                          const char *c_typed_data_json,
+                          const char *c_master_key_json,
+                          const char *c_path,
+                          const char *c_id);
+
+char *export_xpub(const char *c_master_key_json, const char *c_path, const char *c_version);
+
+void free_char(char *data_ptr);
+
+void free_client_shim(ClientShim *c_client_shim);
+
+char *get_address(const char *c_master_key_json, const char *c_path, const char *c_chain);
+
+char *get_client_master_key(const char *c_endpoint, const char *c_auth_token);
+
+char *get_client_master_key_step(const char *c_endpoint,
+                                 const char *c_auth_token,
+                                 const char *c_checkpoint_json);
+
+char *get_client_master_key_with_client(const ClientShim *c_client_shim);
+
+char *get_public_share_key(const char *c_party2_public_key_json);
+
+char *get_public_share_key_with_derive(const char *c_master_key_json,
+                                       int32_t c_x_pos,
+                                       int32_t c_y_pos);
+
+char *get_public_share_key_with_path(const char *c_master_key_json, const char *c_path);
+
+char *hello(void);
+
+char *key_derive(const char *c_master_key_json, int32_t c_x_pos, int32_t c_y_pos);
+
+char *key_derive_path(const char *c_master_key_json, const char *c_path);
+
+uint32_t mpc_client_abi_version(void);
+
+char *mpc_client_version(void);
+
+char *new_client_shim(const char *c_endpoint,
+                      const char *c_auth_token,
+                      const char *c_config_json,
+                      ClientShim **c_client_shim_out);
+
+char *open_private_share(const char *c_sealed_json, const char *c_password);
+
+char *presign_pool_discard(const char *c_pool_json);
+
+char *presign_pool_fill(const char *c_endpoint,
+                        const char *c_auth_token,
+                        const char *c_pool_json,
+                        const char *c_id,
+                        int32_t c_count);
+
+char *rotate_private_share_begin(const char *c_endpoint,
+                                 const char *c_auth_token,
+                                 const char *c_private_share_json);
+
+char *rotate_private_share_commit(const char *c_endpoint,
+                                  const char *c_auth_token,
+                                  const char *c_id);
+
+char *seal_private_share(const char *c_private_share_json, const char *c_password);
+
+char *sign_message(const char *c_endpoint,
+                   const char *c_auth_token,
+                   const char *c_message_le_hex,
+                   const char *c_master_key_json,
+                   int32_t c_x_pos,
+                   int32_t c_y_pos,
+                   const char *c_id);
+
+char *sign_message_presigned(const char *c_endpoint,
+                             const char *c_auth_token,
+                             const char *c_pool_json,
+                             const char *c_message_le_hex,
+                             const char *c_master_key_json,
+                             const char *c_path);
+
+char *sign_message_with_client(const ClientShim *c_client_shim,
+                               const char *c_message_le_hex,
+                               const char *c_master_key_json,
+                               int32_t c_x_pos,
+                               int32_t c_y_pos,
+                               const char *c_id);
+
+char *sign_message_with_format(const char *c_endpoint,
+                               const char *c_auth_token,
+                               const char *c_message_le_hex,
+                               const char *c_master_key_json,
+                               const char *c_path,
+                               const char *c_id,
+                               const char *c_format);
+
+char *sign_message_with_path(const char *c_endpoint,
+                             const char *c_auth_token,
+                             const char *c_message_le_hex,
+                             const char *c_master_key_json,
+                             const char *c_path,
+                             const char *c_id);
+
+char *sign_messages_batch(const char *c_endpoint,
+                          const char *c_auth_token,
+                          const char *c_items_json,
+                          const char *c_master_key_json,
+                          const char *c_id);
+
+char *xpub_derive_public_key(const char *c_xpub, const char *c_path);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* BW_MPC_CLIENT_H */
@@ -0,0 +1,139 @@
+//! JNI exports behind the `jni` feature, for `zone.bitverse.mpc.MpcClient`
+//! in bindings/kotlin.
+//!
+//! Each export calls the C function of the same purpose and hands its
+//! `CResult` JSON to Kotlin as a `String`, releasing the C string here, so the
+//! Kotlin side only parses the result and never calls `free_char`.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::ptr;
+
+use jni::objects::{JClass, JString};
+use jni::sys::{jint, jstring};
+use jni::JNIEnv;
+
+use crate::ecdsa::free::free_char;
+use crate::ecdsa::key_derive::key_derive_path;
+use crate::ecdsa::keygen::{get_client_master_key, get_public_share_key_with_path};
+use crate::ecdsa::sign::sign_message_with_path;
+use crate::error::ClientError;
+use crate::utilities::error_to_c_string;
+use crate::version::{mpc_client_abi_version, mpc_client_version};
+
+/// Copies a Java string into a C string for the FFI functions.
+fn c_string(env: &JNIEnv, s: JString, name: &str) -> Result<CString, ClientError> {
+    let s: String = match env.get_string(s) {
+        Ok(s) => s.into(),
+        Err(e) => return Err(ClientError::InvalidInput(format!("decoding {} failed: {}", name, e)))
+    };
+    CString::new(s).map_err(|e| ClientError::InvalidInput(format!("{} contains a nul byte: {}", name, e)))
+}
+
+/// Turns a `CResult` returned by an FFI function into a Java string and
+/// frees it.
+fn into_jstring(env: &JNIEnv, c_result: *mut c_char) -> jstring {
+    let result = unsafe { CStr::from_ptr(c_result) }.to_string_lossy().into_owned();
+    free_char(c_result);
+    match env.new_string(result) {
+        Ok(s) => s.into_inner(),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Decodes the Java arguments, then calls `f` with them.
+fn call<F>(env: &JNIEnv, args: Vec<(JString, &str)>, f: F) -> jstring
+    where F: FnOnce(&[CString]) -> *mut c_char
+{
+    let mut c_args = Vec::with_capacity(args.len());
+    for (arg, name) in args {
+        match c_string(env, arg, name) {
+            Ok(s) => c_args.push(s),
+            Err(e) => return into_jstring(env, error_to_c_string(e.into())),
+        }
+    }
+    into_jstring(env, f(&c_args))
+}
+
+#[no_mangle]
+pub extern "system" fn Java_zone_bitverse_mpc_MpcClient_nativeVersion(
+    env: JNIEnv,
+    _class: JClass,
+) -> jstring {
+    into_jstring(&env, mpc_client_version())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_zone_bitverse_mpc_MpcClient_nativeAbiVersion(
+    _env: JNIEnv,
+    _class: JClass,
+) -> jint {
+    mpc_client_abi_version() as jint
+}
+
+#[no_mangle]
+pub extern "system" fn Java_zone_bitverse_mpc_MpcClient_nativeKeygen(
+    env: JNIEnv,
+    _class: JClass,
+    endpoint: JString,
+    auth_token: JString,
+) -> jstring {
+    call(&env, vec![(endpoint, "endpoint"), (auth_token, "auth_token")], |args| {
+        get_client_master_key(args[0].as_ptr(), args[1].as_ptr())
+    })
+}
+
+#[no_mangle]
+pub extern "system" fn Java_zone_bitverse_mpc_MpcClient_nativeDerive(
+    env: JNIEnv,
+    _class: JClass,
+    master_key_json: JString,
+    path: JString,
+) -> jstring {
+    call(&env, vec![(master_key_json, "master_key_json"), (path, "path")], |args| {
+        key_derive_path(args[0].as_ptr(), args[1].as_ptr())
+    })
+}
+
+#[no_mangle]
+pub extern "system" fn Java_zone_bitverse_mpc_MpcClient_nativePublicKey(
+    env: JNIEnv,
+    _class: JClass,
+    master_key_json: JString,
+    path: JString,
+) -> jstring {
+    call(&env, vec![(master_key_json, "master_key_json"), (path, "path")], |args| {
+        get_public_share_key_with_path(args[0].as_ptr(), args[1].as_ptr())
+    })
+}
+
+#[no_mangle]
+pub extern "system" fn Java_zone_bitverse_mpc_MpcClient_nativeSign(
+    env: JNIEnv,
+    _class: JClass,
+    endpoint: JString,
+    auth_token: JString,
+    message_hex: JString,
+    master_key_json: JString,
+    path: JString,
+    id: JString,
+) -> jstring {
+    let args = vec![
+        (endpoint, "endpoint"),
+        (auth_token, "auth_token"),
+        (message_hex, "message_hex"),
+        (master_key_json, "master_key_json"),
+        (path, "path"),
+        (id, "id"),
+    ];
+    call(&env, args, |args| {
+        sign_message_with_path(
+            args[0].as_ptr(),
+            args[1].as_ptr(),
+            args[2].as_ptr(),
+            args[3].as_ptr(),
+            args[4].as_ptr(),
+            args[5].as_ptr(),
+        )
+    })
+}
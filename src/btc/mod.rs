@@ -0,0 +1,254 @@
+//! Bitcoin helpers on top of the two-party signature: P2PKH, P2SH-P2WPKH and
+//! P2WPKH addresses and PSBT signing.
+
+use std::ffi::CStr;
+use std::fmt;
+use std::os::raw::c_char;
+use std::str::FromStr;
+
+use bitcoin::{Address, Network, PublicKey};
+use curv::elliptic::curves::secp256_k1::GE;
+use curv::elliptic::curves::traits::ECPoint;
+use kms::ecdsa::two_party::MasterKey2;
+
+use crate::ecdsa::DerivationPath;
+use crate::error::ClientError;
+use crate::utilities::{catch_panic, error_to_c_string, success_to_c_string, to_fixed_bytes};
+
+use super::Result;
+
+pub mod psbt;
+
+pub use self::psbt::sign_psbt;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressType {
+    /// Legacy `1…` addresses.
+    P2pkh,
+    /// Nested segwit `3…` addresses.
+    P2shP2wpkh,
+    /// Native segwit `bc1q…` addresses.
+    P2wpkh,
+}
+
+impl FromStr for AddressType {
+    type Err = ClientError;
+
+    fn from_str(s: &str) -> std::result::Result<AddressType, ClientError> {
+        match s {
+            "p2pkh" => Ok(AddressType::P2pkh),
+            "p2sh_p2wpkh" => Ok(AddressType::P2shP2wpkh),
+            "p2wpkh" => Ok(AddressType::P2wpkh),
+            _ => Err(ClientError::InvalidInput(format!("unknown address type {}", s)))
+        }
+    }
+}
+
+impl fmt::Display for AddressType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AddressType::P2pkh => write!(f, "p2pkh"),
+            AddressType::P2shP2wpkh => write!(f, "p2sh_p2wpkh"),
+            AddressType::P2wpkh => write!(f, "p2wpkh"),
+        }
+    }
+}
+
+/// The compressed public key of `q`.
+pub fn public_key(q: &GE) -> Result<PublicKey> {
+    let bytes = to_fixed_bytes(&q.bytes_compressed_to_big_int(), 33);
+    match PublicKey::from_slice(&bytes) {
+        Ok(pk) => Ok(pk),
+        Err(e) => Err(ClientError::KeyFormat(format!("invalid public key: {}", e)).into())
+    }
+}
+
+pub fn address(q: &GE, address_type: AddressType, network: Network) -> Result<Address> {
+    let pk = public_key(q)?;
+    let address = match address_type {
+        AddressType::P2pkh => Ok(Address::p2pkh(&pk, network)),
+        AddressType::P2shP2wpkh => Address::p2shwpkh(&pk, network),
+        AddressType::P2wpkh => Address::p2wpkh(&pk, network),
+    };
+    address.map_err(|e| ClientError::KeyFormat(format!("{} address: {}", address_type, e)).into())
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BtcAddress {
+    address: String,
+    address_type: AddressType,
+    public_key: String,
+    path: DerivationPath,
+}
+
+/// Address of the child key at `c_path`, e.g. `m/84/0/0/0/0`, for
+/// `c_address_type` (`p2pkh`, `p2sh_p2wpkh` or `p2wpkh`) on `c_network`
+/// (`bitcoin`, `testnet`, `signet` or `regtest`).
+#[no_mangle]
+pub extern "C" fn btc_get_address(
+    c_master_key_json: *const c_char,//masterkey
+    c_path: *const c_char,
+    c_address_type: *const c_char,
+    c_network: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_master_key_json = unsafe { CStr::from_ptr(c_master_key_json) };
+        let master_key_json = match raw_master_key_json.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw master_key_json failed: {}", e)).into())
+        };
+
+        let raw_path = unsafe { CStr::from_ptr(c_path) };
+        let path: DerivationPath = match raw_path.to_str() {
+            Ok(s) => match s.parse() {
+                Ok(p) => p,
+                Err(e) => return error_to_c_string(failure::Error::from(e))
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw path failed: {}", e)).into())
+        };
+
+        let raw_address_type = unsafe { CStr::from_ptr(c_address_type) };
+        let address_type: AddressType = match raw_address_type.to_str() {
+            Ok(s) => match s.parse() {
+                Ok(t) => t,
+                Err(e) => return error_to_c_string(failure::Error::from(e))
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw address_type failed: {}", e)).into())
+        };
+
+        let raw_network = unsafe { CStr::from_ptr(c_network) };
+        let network: Network = match raw_network.to_str() {
+            Ok(s) => match s.parse() {
+                Ok(n) => n,
+                Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("unknown network {}: {}", s, e)).into())
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw network failed: {}", e)).into())
+        };
+
+        let mk: MasterKey2 = match serde_json::from_str(master_key_json){
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::KeyFormat(format!("decoding master_key_json to MasterKey2 failed: {}", e)).into())
+        };
+
+        let mk_child = mk.get_child(path.to_bigints());
+
+        let public_key = match public_key(&mk_child.public.q) {
+            Ok(pk) => pk,
+            Err(e) => return error_to_c_string(e)
+        };
+        match address(&mk_child.public.q, address_type, network) {
+            Ok(address) => success_to_c_string(BtcAddress {
+                address: address.to_string(),
+                address_type,
+                public_key: public_key.to_string(),
+                path,
+            }),
+            Err(e) => error_to_c_string(e)
+        }
+    })
+}
+
+#[test]
+fn test_address() {
+    use curv::elliptic::curves::secp256_k1::FE;
+    use curv::elliptic::curves::traits::ECScalar;
+    use curv::BigInt;
+
+    // private key 1
+    let one: FE = ECScalar::from(&BigInt::from(1));
+    let g: GE = ECPoint::generator();
+    let q = g * &one;
+
+    assert_eq!(public_key(&q).unwrap().to_string(), "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798");
+    assert_eq!(address(&q, AddressType::P2pkh, Network::Bitcoin).unwrap().to_string(), "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH");
+    assert_eq!(address(&q, AddressType::P2shP2wpkh, Network::Bitcoin).unwrap().to_string(), "3JvL6Ymt8MVWiCNHC7oWU6nLeHNJKLZGLN");
+    assert_eq!(address(&q, AddressType::P2shP2wpkh, Network::Testnet).unwrap().to_string(), "2NAUYAHhujozruyzpsFRP63mbrdaU5wnEpN");
+    assert_eq!(address(&q, AddressType::P2wpkh, Network::Bitcoin).unwrap().to_string(), "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+    assert_eq!("p2sh_p2wpkh".parse::<AddressType>().unwrap(), AddressType::P2shP2wpkh);
+    assert!("p2tr".parse::<AddressType>().is_err());
+}
+
+#[cfg(all(test, feature = "local-party-one"))]
+mod end_to_end {
+    use bitcoin::blockdata::transaction::{OutPoint, SigHashType, Transaction, TxIn, TxOut};
+    use bitcoin::hashes::Hash;
+    use bitcoin::secp256k1::{Message, Secp256k1, Signature};
+    use bitcoin::util::bip143::SigHashCache;
+    use bitcoin::util::psbt::PartiallySignedTransaction;
+    use bitcoin::{Network, Script};
+
+    use crate::ecdsa::server::test_share;
+
+    use super::{address, psbt, public_key, sign_psbt, AddressType};
+
+    #[test]
+    fn test_sign_psbt() {
+        let share = test_share("m/84/0/0/0/0");
+        let q = share.child.public.q;
+        let utxo = TxOut {
+            value: 100_000,
+            script_pubkey: address(&q, AddressType::P2wpkh, Network::Bitcoin).unwrap().script_pubkey(),
+        };
+
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn { previous_output: OutPoint::null(), script_sig: Script::new(), sequence: 0xffffffff, witness: vec![] }],
+            output: vec![TxOut { value: 90_000, script_pubkey: utxo.script_pubkey.clone() }],
+        };
+        let mut unsigned = PartiallySignedTransaction::from_unsigned_tx(tx.clone()).unwrap();
+        unsigned.inputs[0].witness_utxo = Some(utxo);
+
+        let mut signed = psbt::decode_psbt(&psbt::encode_psbt(&unsigned)).unwrap();
+        let signed_inputs = sign_psbt(&share.client_shim, &mut signed, &share.child, &share.path, &share.private_share.id, false).unwrap();
+        assert_eq!(signed_inputs, vec![0]);
+
+        let pk = public_key(&q).unwrap();
+        let sig = &signed.inputs[0].partial_sigs[&pk];
+        assert_eq!(*sig.last().unwrap(), SigHashType::All.as_u32() as u8);
+
+        let script_code = address(&q, AddressType::P2pkh, Network::Bitcoin).unwrap().script_pubkey();
+        let sighash = SigHashCache::new(&tx).signature_hash(0, &script_code, 100_000, SigHashType::All);
+        let message = Message::from_slice(&sighash.into_inner()).unwrap();
+        let signature = Signature::from_der(&sig[..sig.len() - 1]).unwrap();
+        Secp256k1::verification_only().verify(&message, &signature, &pk.key).unwrap();
+    }
+
+    #[test]
+    fn test_sign_psbt_refuses_unsafe_inputs() {
+        use crate::error::{error_code, INVALID_INPUT_CODE};
+
+        let share = test_share("m/84/0/0/0/0");
+        let q = share.child.public.q;
+        let utxo = TxOut {
+            value: 100_000,
+            script_pubkey: address(&q, AddressType::P2wpkh, Network::Bitcoin).unwrap().script_pubkey(),
+        };
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![TxIn { previous_output: OutPoint::null(), script_sig: Script::new(), sequence: 0xffffffff, witness: vec![] }],
+            output: vec![TxOut { value: 90_000, script_pubkey: utxo.script_pubkey.clone() }],
+        };
+        let mut unsigned = PartiallySignedTransaction::from_unsigned_tx(tx.clone()).unwrap();
+        unsigned.inputs[0].witness_utxo = Some(utxo);
+        let sign = |psbt: &PartiallySignedTransaction, allow_any_sighash: bool| {
+            let mut psbt = psbt.clone();
+            sign_psbt(&share.client_shim, &mut psbt, &share.child, &share.path, &share.private_share.id, allow_any_sighash).map(|_| psbt)
+        };
+
+        // SIGHASH_SINGLE lets others add inputs and outputs, only signed on request
+        let mut single = unsigned.clone();
+        single.inputs[0].sighash_type = Some(SigHashType::Single);
+        assert_eq!(error_code(&sign(&single, false).unwrap_err()), INVALID_INPUT_CODE);
+        let signed = sign(&single, true).unwrap();
+        let sig = &signed.inputs[0].partial_sigs[&public_key(&q).unwrap()];
+        assert_eq!(*sig.last().unwrap(), SigHashType::Single.as_u32() as u8);
+
+        // a non_witness_utxo is checked against the outpoint even next to a witness_utxo
+        let mut unrelated = unsigned.clone();
+        unrelated.inputs[0].non_witness_utxo = Some(tx);
+        assert_eq!(error_code(&sign(&unrelated, false).unwrap_err()), INVALID_INPUT_CODE);
+    }
+}
@@ -0,0 +1,224 @@
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+use bitcoin::blockdata::script::Script;
+use bitcoin::blockdata::transaction::{SigHashType, TxOut};
+use bitcoin::consensus::encode;
+use bitcoin::hashes::Hash;
+use bitcoin::util::bip143::SigHashCache;
+use bitcoin::util::psbt::PartiallySignedTransaction;
+use bitcoin::{Address, Network};
+use curv::arithmetic::traits::Converter;
+use curv::BigInt;
+use kms::ecdsa::two_party::MasterKey2;
+
+use crate::ecdsa::{sign, DerivationPath};
+use crate::error::ClientError;
+use crate::utilities::{catch_panic, error_to_c_string, success_to_c_string};
+use crate::ClientShim;
+
+use super::super::Result;
+use super::public_key;
+
+#[derive(Serialize, Deserialize)]
+pub struct SignedPsbt {
+    /// Base64 of the PSBT with our partial signatures added.
+    pub psbt: String,
+    pub signed_inputs: Vec<usize>,
+}
+
+/// How an input spends one of our outputs.
+enum Spend {
+    Legacy(Script),
+    Segwit { value: u64 },
+}
+
+pub fn decode_psbt(psbt_base64: &str) -> Result<PartiallySignedTransaction> {
+    let bytes = match base64::decode(psbt_base64.trim()) {
+        Ok(b) => b,
+        Err(e) => return Err(ClientError::InvalidInput(format!("decoding psbt base64 failed: {}", e)).into())
+    };
+    match encode::deserialize(&bytes) {
+        Ok(psbt) => Ok(psbt),
+        Err(e) => Err(ClientError::InvalidInput(format!("decoding psbt failed: {}", e)).into())
+    }
+}
+
+pub fn encode_psbt(psbt: &PartiallySignedTransaction) -> String {
+    base64::encode(encode::serialize(psbt))
+}
+
+/// Adds a partial signature of `mk`, the child key already derived at
+/// `path`, to every input that spends its P2PKH, P2SH-P2WPKH or P2WPKH
+/// output, and returns the indices of those inputs. Inputs of other keys
+/// are left alone.
+///
+/// Legacy inputs are signed over the whole previous transaction, which
+/// therefore has to be in `non_witness_utxo`; segwit inputs use BIP143.
+///
+/// Inputs asking for a sighash type other than `SIGHASH_ALL` are refused
+/// unless `allow_any_sighash` is set, since they let others change the
+/// inputs or outputs after we signed.
+pub fn sign_psbt(
+    client_shim: &ClientShim,
+    psbt: &mut PartiallySignedTransaction,
+    mk: &MasterKey2,
+    path: &DerivationPath,
+    id: &str,
+    allow_any_sighash: bool,
+) -> Result<Vec<usize>> {
+    let pk = public_key(&mk.public.q)?;
+    // only the scripts matter, the network is irrelevant here
+    let p2pkh = Address::p2pkh(&pk, Network::Bitcoin).script_pubkey();
+    let p2wpkh = match Address::p2wpkh(&pk, Network::Bitcoin) {
+        Ok(address) => address.script_pubkey(),
+        Err(e) => return Err(ClientError::KeyFormat(format!("{}", e)).into())
+    };
+    let p2sh_p2wpkh = match Address::p2shwpkh(&pk, Network::Bitcoin) {
+        Ok(address) => address.script_pubkey(),
+        Err(e) => return Err(ClientError::KeyFormat(format!("{}", e)).into())
+    };
+
+    let tx = psbt.global.unsigned_tx.clone();
+    let mut cache = SigHashCache::new(&tx);
+    let mut signed_inputs = Vec::new();
+
+    for (index, input) in psbt.inputs.iter_mut().enumerate() {
+        let outpoint = match tx.input.get(index) {
+            Some(txin) => txin.previous_output,
+            None => return Err(ClientError::InvalidInput(format!("psbt input {} has no transaction input", index)).into())
+        };
+
+        let prev_output = match &input.non_witness_utxo {
+            Some(prev_tx) => {
+                if prev_tx.txid() != outpoint.txid {
+                    return Err(ClientError::InvalidInput(format!("non_witness_utxo of input {} does not match its outpoint", index)).into())
+                }
+                match prev_tx.output.get(outpoint.vout as usize) {
+                    Some(utxo) => Some(utxo),
+                    None => return Err(ClientError::InvalidInput(format!("input {} spends a missing output", index)).into())
+                }
+            }
+            None => None,
+        };
+
+        let utxo: TxOut = match (&input.witness_utxo, prev_output) {
+            (Some(utxo), Some(prev_output)) if utxo != prev_output => {
+                return Err(ClientError::InvalidInput(format!("witness_utxo of input {} does not match its non_witness_utxo", index)).into())
+            }
+            (Some(utxo), _) => utxo.clone(),
+            (None, Some(prev_output)) => prev_output.clone(),
+            (None, None) => continue,
+        };
+
+        let spend = if utxo.script_pubkey == p2pkh {
+            if input.non_witness_utxo.is_none() {
+                return Err(ClientError::InvalidInput(format!("legacy input {} needs non_witness_utxo", index)).into())
+            }
+            Spend::Legacy(utxo.script_pubkey.clone())
+        } else if utxo.script_pubkey == p2wpkh {
+            Spend::Segwit { value: utxo.value }
+        } else if utxo.script_pubkey == p2sh_p2wpkh && input.redeem_script.as_ref() == Some(&p2wpkh) {
+            Spend::Segwit { value: utxo.value }
+        } else {
+            continue
+        };
+
+        let sighash_type = input.sighash_type.unwrap_or(SigHashType::All);
+        if sighash_type != SigHashType::All && !allow_any_sighash {
+            return Err(ClientError::InvalidInput(format!("input {} asks for {:?}, only SIGHASH_ALL is signed", index, sighash_type)).into())
+        }
+        let sighash = match spend {
+            Spend::Legacy(script_pubkey) => tx.signature_hash(index, &script_pubkey, sighash_type.as_u32()),
+            // BIP143 script code of a P2WPKH output is the P2PKH script
+            Spend::Segwit { value } => cache.signature_hash(index, &p2pkh, value, sighash_type),
+        };
+
+        let message = BigInt::from_bytes(&sighash.into_inner());
+        let signature = sign::sign_with_path(client_shim, message, mk, path, id)?;
+
+        let mut der = sign::to_der(&signature);
+        der.push(sighash_type.as_u32() as u8);
+        input.partial_sigs.insert(pk, der);
+        signed_inputs.push(index);
+    }
+
+    Ok(signed_inputs)
+}
+
+/// Signs the base64 PSBT `c_psbt` with the child key at `c_path` and returns
+/// a `SignedPsbt`. Sighash types other than ALL need `c_allow_any_sighash`.
+#[no_mangle]
+pub extern "C" fn btc_sign_psbt(
+    c_endpoint: *const c_char,
+    c_auth_token: *const c_char,
+    c_psbt: *const c_char,
+    c_master_key_json: *const c_char,//masterkey
+    c_path: *const c_char,//m/84/0/0/0/0
+    c_id: *const c_char,
+    c_allow_any_sighash: bool,
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_endpoint = unsafe { CStr::from_ptr(c_endpoint) };
+        let endpoint = match raw_endpoint.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw endpoint failed: {}", e)).into())
+        };
+
+        let raw_auth_token = unsafe { CStr::from_ptr(c_auth_token) };
+        let auth_token = match raw_auth_token.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw auth_token failed: {}", e)).into())
+        };
+
+        let raw_psbt = unsafe { CStr::from_ptr(c_psbt) };
+        let mut psbt = match raw_psbt.to_str() {
+            Ok(s) => match decode_psbt(s) {
+                Ok(p) => p,
+                Err(e) => return error_to_c_string(e)
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw psbt failed: {}", e)).into())
+        };
+
+        let raw_master_key_json = unsafe { CStr::from_ptr(c_master_key_json) };
+        let master_key_json = match raw_master_key_json.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw master_key_json failed: {}", e)).into())
+        };
+
+        let raw_path = unsafe { CStr::from_ptr(c_path) };
+        let path: DerivationPath = match raw_path.to_str() {
+            Ok(s) => match s.parse() {
+                Ok(p) => p,
+                Err(e) => return error_to_c_string(failure::Error::from(e))
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw path failed: {}", e)).into())
+        };
+
+        let raw_id = unsafe { CStr::from_ptr(c_id) };
+        let id = match raw_id.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw id failed: {}", e)).into())
+        };
+
+        let mk: MasterKey2 = match serde_json::from_str(master_key_json){
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::KeyFormat(format!("decoding master_key_json to MasterKey2 failed: {}", e)).into())
+        };
+
+        let mk_child = mk.get_child(path.to_bigints());
+
+        let client_shim = ClientShim::new(endpoint.to_string(), Some(auth_token.to_string()));
+
+        match sign_psbt(&client_shim, &mut psbt, &mk_child, &path, id, c_allow_any_sighash) {
+            Ok(signed_inputs) => success_to_c_string(SignedPsbt {
+                psbt: encode_psbt(&psbt),
+                signed_inputs,
+            }),
+            Err(e) => {
+                error!("signing psbt failed: {}", e);
+                error_to_c_string(e)
+            }
+        }
+    })
+}
@@ -0,0 +1,180 @@
+//! Registry of the chains the wallet derives keys for, keyed by SLIP-44 coin
+//! type, and the address format of each.
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+use bitcoin::util::base58;
+use bitcoin::Network;
+use curv::elliptic::curves::secp256_k1::GE;
+use kms::ecdsa::two_party::MasterKey2;
+
+use crate::btc::{self, AddressType};
+use crate::ecdsa::DerivationPath;
+use crate::error::ClientError;
+use crate::ethereum;
+use crate::utilities::{catch_panic, error_to_c_string, success_to_c_string};
+
+use super::Result;
+
+/// Version byte of Tron mainnet addresses, the leading `T`.
+pub const TRON_ADDRESS_PREFIX: u8 = 0x41;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressFormat {
+    /// EIP-55 checksummed hex of the Keccak-256 account.
+    Evm,
+    /// Base58Check of the EVM account behind `TRON_ADDRESS_PREFIX`.
+    Tron,
+    Bitcoin(AddressType, Network),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Chain {
+    /// Name accepted by `get_address`.
+    pub name: &'static str,
+    /// SLIP-44 coin type.
+    pub coin_type: u32,
+    /// EIP-155 chain id of EVM chains.
+    pub chain_id: Option<u64>,
+    pub format: AddressFormat,
+}
+
+/// Every supported chain. Several chains may share a coin type; lookups by
+/// coin type return the first one listed.
+pub const CHAINS: &[Chain] = &[
+    Chain { name: "bitcoin", coin_type: 0, chain_id: None, format: AddressFormat::Bitcoin(AddressType::P2wpkh, Network::Bitcoin) },
+    Chain { name: "bitcoin_testnet", coin_type: 1, chain_id: None, format: AddressFormat::Bitcoin(AddressType::P2wpkh, Network::Testnet) },
+    Chain { name: "ethereum", coin_type: 60, chain_id: Some(1), format: AddressFormat::Evm },
+    Chain { name: "ethereum_classic", coin_type: 61, chain_id: Some(61), format: AddressFormat::Evm },
+    Chain { name: "tron", coin_type: 195, chain_id: None, format: AddressFormat::Tron },
+    Chain { name: "polygon", coin_type: 966, chain_id: Some(137), format: AddressFormat::Evm },
+    Chain { name: "fantom", coin_type: 1007, chain_id: Some(250), format: AddressFormat::Evm },
+    Chain { name: "avalanche_c", coin_type: 9000, chain_id: Some(43114), format: AddressFormat::Evm },
+    Chain { name: "bsc", coin_type: 9006, chain_id: Some(56), format: AddressFormat::Evm },
+];
+
+impl Chain {
+    pub fn by_name(name: &str) -> Option<&'static Chain> {
+        CHAINS.iter().find(|c| c.name == name)
+    }
+
+    pub fn by_coin_type(coin_type: u32) -> Option<&'static Chain> {
+        CHAINS.iter().find(|c| c.coin_type == coin_type)
+    }
+
+    /// A chain name, or a SLIP-44 coin type in decimal.
+    pub fn lookup(s: &str) -> Result<&'static Chain> {
+        let chain = match s.parse::<u32>() {
+            Ok(coin_type) => Chain::by_coin_type(coin_type),
+            Err(_) => Chain::by_name(&s.to_ascii_lowercase()),
+        };
+        match chain {
+            Some(c) => Ok(c),
+            None => Err(ClientError::InvalidInput(format!("unknown chain {}", s)).into())
+        }
+    }
+
+    pub fn address(&self, q: &GE) -> Result<String> {
+        match self.format {
+            AddressFormat::Evm => Ok(ethereum::Address::from_public_key(q).to_string()),
+            AddressFormat::Tron => Ok(tron_address(q)),
+            AddressFormat::Bitcoin(address_type, network) => Ok(btc::address(q, address_type, network)?.to_string()),
+        }
+    }
+}
+
+pub fn tron_address(q: &GE) -> String {
+    let mut payload = vec![TRON_ADDRESS_PREFIX];
+    payload.extend_from_slice(&ethereum::Address::from_public_key(q).0);
+    base58::check_encode_slice(&payload)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ChainAddress {
+    chain: String,
+    coin_type: u32,
+    chain_id: Option<u64>,
+    address: String,
+    path: DerivationPath,
+}
+
+/// Address of the child key at `c_path` on `c_chain`, a name from `CHAINS`
+/// (`ethereum`, `bsc`, `tron`, ...) or a SLIP-44 coin type such as `195`.
+///
+/// The path is used as given; it is not checked against the coin type.
+#[no_mangle]
+pub extern "C" fn get_address(
+    c_master_key_json: *const c_char,//masterkey
+    c_path: *const c_char,//m/44/195/0/0/0
+    c_chain: *const c_char,//tron
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_master_key_json = unsafe { CStr::from_ptr(c_master_key_json) };
+        let master_key_json = match raw_master_key_json.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw master_key_json failed: {}", e)).into())
+        };
+
+        let raw_path = unsafe { CStr::from_ptr(c_path) };
+        let path: DerivationPath = match raw_path.to_str() {
+            Ok(s) => match s.parse() {
+                Ok(p) => p,
+                Err(e) => return error_to_c_string(failure::Error::from(e))
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw path failed: {}", e)).into())
+        };
+
+        let raw_chain = unsafe { CStr::from_ptr(c_chain) };
+        let chain = match raw_chain.to_str() {
+            Ok(s) => match Chain::lookup(s) {
+                Ok(c) => c,
+                Err(e) => return error_to_c_string(e)
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw chain failed: {}", e)).into())
+        };
+
+        let mk: MasterKey2 = match serde_json::from_str(master_key_json){
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::KeyFormat(format!("decoding master_key_json to MasterKey2 failed: {}", e)).into())
+        };
+
+        let mk_child = mk.get_child(path.to_bigints());
+
+        match chain.address(&mk_child.public.q) {
+            Ok(address) => success_to_c_string(ChainAddress {
+                chain: chain.name.to_string(),
+                coin_type: chain.coin_type,
+                chain_id: chain.chain_id,
+                address,
+                path,
+            }),
+            Err(e) => error_to_c_string(e)
+        }
+    })
+}
+
+#[test]
+fn test_chain_address() {
+    use curv::elliptic::curves::secp256_k1::FE;
+    use curv::elliptic::curves::traits::{ECPoint, ECScalar};
+    use curv::BigInt;
+
+    // private key 1
+    let one: FE = ECScalar::from(&BigInt::from(1));
+    let g: GE = ECPoint::generator();
+    let q = g * &one;
+
+    assert_eq!(Chain::lookup("tron").unwrap().address(&q).unwrap(), "TMVQGm1qAQYVdetCeGRRkTWYYrLXuHK2HC");
+    assert_eq!(Chain::lookup("195").unwrap().name, "tron");
+    assert_eq!(Chain::lookup("BSC").unwrap().address(&q).unwrap(), "0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf");
+    assert_eq!(Chain::lookup("bsc").unwrap().chain_id, Some(56));
+    assert_eq!(Chain::lookup("60").unwrap().chain_id, Some(1));
+    assert_eq!(Chain::lookup("bitcoin").unwrap().address(&q).unwrap(), "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+    assert!(Chain::lookup("dogecoin").is_err());
+    assert!(Chain::lookup("3").is_err());
+
+    for (i, chain) in CHAINS.iter().enumerate() {
+        assert!(CHAINS[..i].iter().all(|c| c.name != chain.name), "{}", chain.name);
+    }
+}
@@ -0,0 +1,221 @@
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+use curv::BigInt;
+use kms::ecdsa::two_party::MasterKey2;
+use multi_party_ecdsa::protocols::two_party_ecdsa::lindell_2017::party_one;
+
+use crate::error::{error_code, ClientError};
+use crate::utilities::{catch_panic, error_to_c_string, success_to_c_string, SUCCESS_CODE};
+
+use super::super::ClientShim;
+use super::super::Result;
+use super::super::utilities::requests;
+use super::path::DerivationPath;
+use super::sign::{normalize_s, sign_first_request, sign_second_request, verify_signature, SIGN_PATH_PRE};
+use super::ServerReply;
+
+/// Items per batch request; larger batches are split.
+pub const SIGN_BATCH_MAX: usize = 64;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SignItem {
+    pub message: BigInt,
+    pub path: DerivationPath,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SignBatchFirstRequest {
+    pub id: String,
+    pub ephKeyGenFirstMsgs: Vec<String>,
+}
+
+/// `index` is the position of the item in the preceding `SignBatchFirstRequest`.
+#[derive(Serialize, Deserialize)]
+pub struct SignBatchSecondItem {
+    pub index: usize,
+    pub signSecondMsgReq: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SignBatchSecondRequest {
+    pub id: String,
+    pub items: Vec<SignBatchSecondItem>,
+}
+
+/// Per-item outcome of `sign_messages_batch`, shaped like `CResult`.
+#[derive(Serialize)]
+pub struct SignBatchResult {
+    ret_code: i32,
+    ret_msg: String,
+    signature: Option<party_one::SignatureRecid>,
+}
+
+/// Signs every item with the child of the master key `mk` at its path, in
+/// two round trips per `SIGN_BATCH_MAX` items instead of two per item.
+///
+/// The outer error is a failure of the whole batch (network, malformed
+/// reply); otherwise the result has one entry per item, in order.
+pub fn sign_batch(
+    client_shim: &ClientShim,
+    mk: &MasterKey2,
+    items: &[SignItem],
+    id: &str,
+) -> Result<Vec<Result<party_one::SignatureRecid>>> {
+    let mut results = Vec::with_capacity(items.len());
+    for chunk in items.chunks(SIGN_BATCH_MAX) {
+        results.extend(sign_chunk(client_shim, mk, chunk, id)?);
+    }
+    Ok(results)
+}
+
+fn sign_chunk(
+    client_shim: &ClientShim,
+    mk: &MasterKey2,
+    items: &[SignItem],
+    id: &str,
+) -> Result<Vec<Result<party_one::SignatureRecid>>> {
+    let mut states = Vec::with_capacity(items.len());
+    let mut first_msgs = Vec::with_capacity(items.len());
+    for _ in items {
+        let (state, sign_first_req) = sign_first_request(id)?;
+        states.push(state);
+        first_msgs.push(sign_first_req.ephKeyGenFirstMsg);
+    }
+
+    let sign_batch_first_req = SignBatchFirstRequest { id: id.to_string(), ephKeyGenFirstMsgs: first_msgs };
+    let server_reply: ServerReply = requests::postb(client_shim, &format!("{}/batch/first", SIGN_PATH_PRE), &sign_batch_first_req)?;
+    let first_replies: Vec<ServerReply> = server_reply.into_result()?;
+    if first_replies.len() != items.len() {
+        return Err(ClientError::MalformedResponse(format!("{} replies for {} items", first_replies.len(), items.len())).into())
+    }
+
+    let mut results: Vec<Option<Result<party_one::SignatureRecid>>> = items.iter().map(|_| None).collect();
+    let mut second_items = Vec::new();
+    let mut children = Vec::new();
+    for (index, ((item, state), first_reply)) in items.iter().zip(states).zip(first_replies).enumerate() {
+        let child = mk.get_child(item.path.to_bigints());
+        match sign_second_request(&child, state, first_reply, item.message.clone(), item.path.to_bigints(), id) {
+            Ok(req) => {
+                second_items.push(SignBatchSecondItem { index, signSecondMsgReq: req.signSecondMsgReq });
+                children.push((index, child));
+            }
+            Err(e) => results[index] = Some(Err(e)),
+        }
+    }
+
+    if !second_items.is_empty() {
+        let sign_batch_second_req = SignBatchSecondRequest { id: id.to_string(), items: second_items };
+        let server_reply: ServerReply = requests::postb(client_shim, &format!("{}/batch/second", SIGN_PATH_PRE), &sign_batch_second_req)?;
+        let second_replies: Vec<ServerReply> = server_reply.into_result()?;
+        if second_replies.len() != children.len() {
+            return Err(ClientError::MalformedResponse(format!("{} replies for {} items", second_replies.len(), children.len())).into())
+        }
+
+        for ((index, child), second_reply) in children.into_iter().zip(second_replies) {
+            let message = &items[index].message;
+            let signature = second_reply.into_result::<party_one::SignatureRecid>()
+                .and_then(|signature| {
+                    verify_signature(&signature, &child.public.q, message)?;
+                    Ok(normalize_s(signature))
+                });
+            results[index] = Some(signature);
+        }
+    }
+
+    Ok(results.into_iter().map(|r| r.unwrap()).collect())
+}
+
+/// Signs a JSON list of `{"message": <hash as BigInt>, "path": "m/44/60/0/0/1"}`
+/// with children of the master key, returning one `SignBatchResult` per item.
+#[no_mangle]
+pub extern "C" fn sign_messages_batch(
+    c_endpoint: *const c_char,
+    c_auth_token: *const c_char,
+    c_items_json: *const c_char,
+    c_master_key_json: *const c_char,//masterkey
+    c_id: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_endpoint = unsafe { CStr::from_ptr(c_endpoint) };
+        let endpoint = match raw_endpoint.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw endpoint failed: {}", e)).into())
+        };
+
+        let raw_auth_token = unsafe { CStr::from_ptr(c_auth_token) };
+        let auth_token = match raw_auth_token.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw auth_token failed: {}", e)).into())
+        };
+
+        let raw_items_json = unsafe { CStr::from_ptr(c_items_json) };
+        let items: Vec<SignItem> = match raw_items_json.to_str() {
+            Ok(s) => match serde_json::from_str(s) {
+                Ok(items) => items,
+                Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding items_json failed: {}", e)).into())
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw items_json failed: {}", e)).into())
+        };
+
+        let raw_master_key_json = unsafe { CStr::from_ptr(c_master_key_json) };
+        let master_key_json = match raw_master_key_json.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw master_key_json failed: {}", e)).into())
+        };
+
+        let raw_id = unsafe { CStr::from_ptr(c_id) };
+        let id = match raw_id.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw id failed: {}", e)).into())
+        };
+
+        let mk: MasterKey2 = match serde_json::from_str(master_key_json){
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::KeyFormat(format!("decoding master_key_json to MasterKey2 failed: {}", e)).into())
+        };
+
+        let client_shim = ClientShim::new(endpoint.to_string(), Some(auth_token.to_string()));
+
+        match sign_batch(&client_shim, &mk, &items, id) {
+            Ok(results) => {
+                let results: Vec<SignBatchResult> = results.into_iter().map(|r| match r {
+                    Ok(signature) => SignBatchResult { ret_code: SUCCESS_CODE, ret_msg: "OK".to_string(), signature: Some(signature) },
+                    Err(e) => SignBatchResult { ret_code: error_code(&e), ret_msg: format!("Error: {}", e), signature: None },
+                }).collect();
+                success_to_c_string(results)
+            }
+            Err(e) => {
+                error!("batch signing failed: {}", e);
+                error_to_c_string(e)
+            }
+        }
+    })
+}
+
+#[cfg(all(test, feature = "local-party-one"))]
+mod end_to_end {
+    use curv::BigInt;
+
+    use crate::ecdsa::server::test_share;
+
+    use super::{sign_batch, verify_signature, SignItem};
+
+    #[test]
+    fn test_sign_batch() {
+        let share = test_share("m/44/0/0/0/0");
+
+        let items: Vec<SignItem> = (0..5u32).map(|i| SignItem {
+            message: BigInt::from(1000 + i as u64),
+            path: format!("m/44/0/0/0/{}", i).parse().unwrap(),
+        }).collect();
+
+        let results = sign_batch(&share.client_shim, &share.private_share.master_key, &items, &share.private_share.id).unwrap();
+        assert_eq!(results.len(), items.len());
+        for (item, result) in items.iter().zip(results) {
+            let signature = result.unwrap();
+            let child = share.private_share.get_child(item.path.to_bigints());
+            verify_signature(&signature, &child.master_key.public.q, &item.message).unwrap();
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+use crate::error::ClientError;
+use crate::utilities::{catch_panic, error_to_c_string, success_to_c_string};
+use crate::{ClientConfig, ClientShim};
+
+/// Creates a client with connect/request timeouts and retry policy taken
+/// from `c_config_json` (see `ClientConfig`; an empty string uses defaults).
+///
+/// On success `*c_client_shim_out` receives the client, which must be
+/// released with `free_client_shim`.
+#[no_mangle]
+pub extern "C" fn new_client_shim(
+    c_endpoint: *const c_char,
+    c_auth_token: *const c_char,
+    c_config_json: *const c_char,
+    c_client_shim_out: *mut *mut ClientShim,
+) -> *mut c_char {
+    catch_panic(|| {
+        if c_client_shim_out.is_null() {
+            return error_to_c_string(ClientError::InvalidInput("client_shim_out is null".to_string()).into())
+        }
+
+        let raw_endpoint = unsafe { CStr::from_ptr(c_endpoint) };
+        let endpoint = match raw_endpoint.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw endpoint failed: {}", e)).into())
+        };
+
+        let raw_auth_token = unsafe { CStr::from_ptr(c_auth_token) };
+        let auth_token = match raw_auth_token.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw auth_token failed: {}", e)).into())
+        };
+
+        let raw_config_json = unsafe { CStr::from_ptr(c_config_json) };
+        let config_json = match raw_config_json.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw config_json failed: {}", e)).into())
+        };
+
+        let config: ClientConfig = if config_json.trim().is_empty() {
+            ClientConfig::default()
+        } else {
+            match serde_json::from_str(config_json) {
+                Ok(c) => c,
+                Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding config_json failed: {}", e)).into())
+            }
+        };
+
+        let client_shim = match ClientShim::with_config(endpoint.to_string(), Some(auth_token.to_string()), config.clone()) {
+            Ok(c) => c,
+            Err(e) => return error_to_c_string(e)
+        };
+
+        unsafe {
+            *c_client_shim_out = Box::into_raw(Box::new(client_shim));
+        }
+        success_to_c_string(config)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn free_client_shim(c_client_shim: *mut ClientShim) {
+    if c_client_shim.is_null() {
+        return
+    }
+    unsafe {
+        let _client_shim = Box::from_raw(c_client_shim);
+    }
+}
@@ -3,7 +3,16 @@ use std::os::raw::c_char;
 
 use curv::BigInt;
 use kms::ecdsa::two_party::MasterKey2;
-use crate::utilities::{error_to_c_string, success_to_c_string};
+use crate::error::ClientError;
+use crate::utilities::{catch_panic, error_to_c_string, success_to_c_string};
+
+use super::path::DerivationPath;
+
+/// The child of `master_key` at `path`, as returned by `key_derive_path` and
+/// `get_public_share_key_with_path`.
+pub fn derive_child(master_key: &MasterKey2, path: &DerivationPath) -> MasterKey2 {
+    master_key.get_child(path.to_bigints())
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct MasterDeriveKey {
@@ -18,39 +27,168 @@ pub extern "C" fn key_derive(
     c_x_pos: i32,//coinType
     c_y_pos: i32,//account:1,2
 ) -> *mut c_char {
+    catch_panic(|| {
 
-    let x: BigInt = BigInt::from(c_x_pos);
-    let y: BigInt = BigInt::from(c_y_pos);
+        let x: BigInt = BigInt::from(c_x_pos);
+        let y: BigInt = BigInt::from(c_y_pos);
 
-    let raw_master_key_json = unsafe { CStr::from_ptr(c_master_key_json) };
-    let master_key_json = match raw_master_key_json.to_str() {
-        Ok(s) => s,
-        Err(e) => return error_to_c_string(format_err!("decoding raw master_key_json failed: {}", e))
-    };
+        let raw_master_key_json = unsafe { CStr::from_ptr(c_master_key_json) };
+        let master_key_json = match raw_master_key_json.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw master_key_json failed: {}", e)).into())
+        };
 
-    let master_key: MasterKey2 = match serde_json::from_str(master_key_json){
-        Ok(s) => s,
-        Err(e) => {
-            return error_to_c_string(format_err!("decoding master_key_json to MasterKey2 failed: {}", e))
-        }
-    };
+        let master_key: MasterKey2 = match serde_json::from_str(master_key_json){
+            Ok(s) => s,
+            Err(e) => {
+                return error_to_c_string(ClientError::KeyFormat(format!("decoding master_key_json to MasterKey2 failed: {}", e)).into())
+            }
+        };
 
-   let child_master_key =  master_key.get_child(vec![x.clone(), y.clone()]);
+        let child_master_key =  master_key.get_child(vec![x.clone(), y.clone()]);
 
-    let derive_key = MasterDeriveKey{
-        master_key:child_master_key,
-        x_pos:x,
-        y_pos:y
-    };
+        let derive_key = MasterDeriveKey{
+            master_key:child_master_key,
+            x_pos:x,
+            y_pos:y
+        };
 
-    return success_to_c_string(derive_key)
+        return success_to_c_string(derive_key)
+    })
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct MasterDerivePathKey {
+    master_key: MasterKey2,
+    path: DerivationPath,
+}
+
+#[no_mangle]
+pub extern "C" fn key_derive_path(
+    c_master_key_json: *const c_char,//masterkey
+    c_path: *const c_char,//m/44/60/0/0/5
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_master_key_json = unsafe { CStr::from_ptr(c_master_key_json) };
+        let master_key_json = match raw_master_key_json.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw master_key_json failed: {}", e)).into())
+        };
+
+        let raw_path = unsafe { CStr::from_ptr(c_path) };
+        let path: DerivationPath = match raw_path.to_str() {
+            Ok(s) => match s.parse() {
+                Ok(p) => p,
+                Err(e) => return error_to_c_string(failure::Error::from(e))
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw path failed: {}", e)).into())
+        };
+
+        let master_key: MasterKey2 = match serde_json::from_str(master_key_json){
+            Ok(s) => s,
+            Err(e) => {
+                return error_to_c_string(ClientError::KeyFormat(format!("decoding master_key_json to MasterKey2 failed: {}", e)).into())
+            }
+        };
+
+        let child_master_key = derive_child(&master_key, &path);
+
+        let derive_key = MasterDerivePathKey{
+            master_key:child_master_key,
+            path,
+        };
+
+        return success_to_c_string(derive_key)
+    })
+}
 
 #[test]
 fn test_key_derive() {
     let fs = std::fs::read_to_string("testAssets/mk.json").unwrap();
-    let rt = key_derive(CString::new(fs).unwrap().into_raw(), 60,0);
+    let private_share: serde_json::Value = serde_json::from_str(&fs).unwrap();
+    let mk = private_share["master_key"].to_string();
+    let rt = key_derive(CString::new(mk).unwrap().into_raw(), 60,0);
+    let c_result = unsafe { CString::from_raw(rt) };
+    let c_result: serde_json::Value = serde_json::from_str(c_result.to_str().unwrap()).unwrap();
+    assert_eq!(c_result["ret_code"], 0);
+
+    let derived: MasterDeriveKey = serde_json::from_str(c_result["result"].as_str().unwrap()).unwrap();
+    let vectors = derive_vectors();
+    let expected = vectors["vectors"].as_array().unwrap().iter().find(|v| v["path"] == "m/60/0").unwrap();
+    assert_key(&derived.master_key, expected);
 }
 
+#[cfg(test)]
+pub(crate) fn derive_vectors() -> serde_json::Value {
+    let fs = std::fs::read_to_string("testAssets/derive_vectors.json").unwrap();
+    serde_json::from_str(&fs).unwrap()
+}
+
+#[cfg(test)]
+pub(crate) fn vector_point(v: &serde_json::Value) -> curv::elliptic::curves::secp256_k1::GE {
+    use curv::elliptic::curves::traits::ECPoint;
+    curv::elliptic::curves::secp256_k1::GE::from_bytes(&hex::decode(v.as_str().unwrap()).unwrap()).unwrap()
+}
+
+#[cfg(test)]
+fn assert_key(mk: &MasterKey2, expected: &serde_json::Value) {
+    use curv::arithmetic::traits::Converter;
+    assert_eq!(mk.public.q, vector_point(&expected["q"]), "q at {}", expected["path"]);
+    assert_eq!(mk.public.p2, vector_point(&expected["p2"]), "p2 at {}", expected["path"]);
+    assert_eq!(mk.chain_code, BigInt::from_hex(expected["chain_code"].as_str().unwrap()), "chain code at {}", expected["path"]);
+}
 
+#[test]
+fn test_key_derive_path() {
+    let fs = std::fs::read_to_string("testAssets/mk.json").unwrap();
+    let private_share: serde_json::Value = serde_json::from_str(&fs).unwrap();
+    let mk = private_share["master_key"].to_string();
+    let rt = key_derive_path(CString::new(mk).unwrap().into_raw(), CString::new("m/44/60/0/0/5").unwrap().into_raw());
+    let c_result = unsafe { CString::from_raw(rt) };
+    let c_result: serde_json::Value = serde_json::from_str(c_result.to_str().unwrap()).unwrap();
+    assert_eq!(c_result["ret_code"], 0);
+
+    let rt = key_derive_path(CString::new("{}").unwrap().into_raw(), CString::new("m/44'/60").unwrap().into_raw());
+    let c_result = unsafe { CString::from_raw(rt) };
+    let c_result: serde_json::Value = serde_json::from_str(c_result.to_str().unwrap()).unwrap();
+    assert_eq!(c_result["ret_code"], crate::error::INVALID_INPUT_CODE);
+}
+
+#[test]
+fn test_derive_vectors() {
+    use curv::elliptic::curves::traits::ECPoint;
+
+    let vectors = derive_vectors();
+    let fs = std::fs::read_to_string(vectors["master_key"].as_str().unwrap()).unwrap();
+    let private_share: super::PrivateShare = serde_json::from_str(&fs).unwrap();
+    let mk_json = serde_json::to_string(&private_share.master_key).unwrap();
+    assert_key(&private_share.master_key, &vectors["public"]);
+
+    for expected in vectors["vectors"].as_array().unwrap() {
+        let path = expected["path"].as_str().unwrap();
+        let rt = key_derive_path(CString::new(mk_json.clone()).unwrap().into_raw(), CString::new(path).unwrap().into_raw());
+        let c_result = unsafe { CString::from_raw(rt) };
+        let c_result: serde_json::Value = serde_json::from_str(c_result.to_str().unwrap()).unwrap();
+        assert_eq!(c_result["ret_code"], 0, "{}", path);
+        let derived: MasterDerivePathKey = serde_json::from_str(c_result["result"].as_str().unwrap()).unwrap();
+        assert_eq!(derived.path.to_string(), path);
+        assert_key(&derived.master_key, expected);
+
+        // the (coinType, account) entry points cover the two level paths
+        let indices = derived.path.indices();
+        if indices.len() == 2 && indices.iter().all(|i| *i <= i32::MAX as u32) {
+            let (x, y) = (indices[0] as i32, indices[1] as i32);
+            let rt = super::keygen::get_public_share_key_with_derive(CString::new(mk_json.clone()).unwrap().into_raw(), x, y);
+            let c_result = unsafe { CString::from_raw(rt) };
+            let c_result: serde_json::Value = serde_json::from_str(c_result.to_str().unwrap()).unwrap();
+            let pk: serde_json::Value = serde_json::from_str(c_result["result"].as_str().unwrap()).unwrap();
+            assert_eq!(pk, serde_json::to_value(vector_point(&expected["q"]).get_element()).unwrap(), "{}", path);
+
+            let rt = key_derive(CString::new(mk_json.clone()).unwrap().into_raw(), x, y);
+            let c_result = unsafe { CString::from_raw(rt) };
+            let c_result: serde_json::Value = serde_json::from_str(c_result.to_str().unwrap()).unwrap();
+            let derived: MasterDeriveKey = serde_json::from_str(c_result["result"].as_str().unwrap()).unwrap();
+            assert_key(&derived.master_key, expected);
+        }
+    }
+}
@@ -5,6 +5,8 @@ use std::os::raw::c_char;
 use std::time::Instant;
 
 use curv::BigInt;
+use curv::cryptographic_primitives::commitments::hash_commitment::HashCommitment;
+use curv::cryptographic_primitives::commitments::traits::Commitment;
 use curv::cryptographic_primitives::proofs::sigma_dlog::DLogProof;
 use curv::cryptographic_primitives::twoparty::dh_key_exchange_variant_with_pok_comm::*;
 use curv::elliptic::curves::secp256_k1::GE;
@@ -13,146 +15,366 @@ use floating_duration::TimeFormat;
 use kms::chain_code::two_party as chain_code;
 use kms::ecdsa::two_party::*;
 use multi_party_ecdsa::protocols::two_party_ecdsa::lindell_2017::*;
-use serde_json::{self, ser};
+use serde_json;
 use zk_paillier::zkproofs::SALT_STRING;
 
 use crate::ecdsa::ServerReply;
-use crate::utilities::{SUCCESS_CODE, success_to_c_string, error_to_c_string};
+use crate::error::{ClientError, KeygenProof, KeygenProofError};
+use crate::utilities::{catch_panic, success_to_c_string, error_to_c_string};
 
 // use super::super::utilities::requests;
 use super::super::ClientShim;
 use super::super::utilities::requests;
+#[cfg(feature = "async")]
+use super::super::AsyncClientShim;
+#[cfg(feature = "async")]
+use super::super::utilities::async_requests;
+use super::path::DerivationPath;
 use super::types::PrivateShare;
 
-const KG_PATH_PRE: &str = "bitverse/wallet/v1/private/mpc/ecdsa/keygen";
+pub(crate) const KG_PATH_PRE: &str = "bitverse/wallet/v1/private/mpc/ecdsa/keygen";
 
 
 #[derive(Serialize, Deserialize)]
 pub struct KeyGenSecondReq{
-    id : String,
-    d_log_proof:String,
+    pub id : String,
+    pub d_log_proof:String,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct ChainCodeFirstReq{
-    id : String,
+    pub id : String,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct ChainCodeSecondReq{
-    id : String,
-    d_log_proof: String,
+    pub id : String,
+    pub d_log_proof: String,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct KeyGenAbandonReq{
+    pub id : String,
+}
 
-pub fn get_master_key(client_shim: &ClientShim) -> Result<PrivateShare> {
-    let start = Instant::now();
 
-    let server_reply:ServerReply = match requests::post(&client_shim, &format!("{}/first", KG_PATH_PRE)){
-        Some(s) => s,
-        None =>  return Err(format_err!("{}","Net error!"))
-    };
+/// Progress of a two-party keygen, persisted by the app between round trips.
+///
+/// Every variant holds what party two needs to send the next request,
+/// including the messages it generated locally, so a resumed keygen repeats
+/// exactly the request that may have been lost. The checkpoint carries secret
+/// key material and must be stored as carefully as the final `PrivateShare`.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum KeygenCheckpoint {
+    Start,
+    KeyGenFirst {
+        id: String,
+        kg_party_one_first_message: party_one::KeyGenFirstMsg,
+        kg_party_two_first_message: party_two::KeyGenFirstMsg,
+        kg_ec_key_pair_party2: party_two::EcKeyPair,
+    },
+    KeyGenSecond {
+        id: String,
+        kg_ec_key_pair_party2: party_two::EcKeyPair,
+        kg_party_one_public_share: GE,
+        party_two_paillier: party_two::PaillierPublic,
+    },
+    ChainCodeFirst {
+        id: String,
+        kg_ec_key_pair_party2: party_two::EcKeyPair,
+        kg_party_one_public_share: GE,
+        party_two_paillier: party_two::PaillierPublic,
+        cc_party_one_first_message: Party1FirstMessage,
+        cc_party_two_first_message: Party2FirstMessage<GE>,
+        cc_ec_key_pair2: EcKeyPair<GE>,
+    },
+    Done(PrivateShare),
+}
 
-    if server_reply.retCode != SUCCESS_CODE || server_reply.result == None {
-        return Err(format_err!("{}:{}",server_reply.retCode,server_reply.retMsg))
+impl KeygenCheckpoint {
+    /// The server session id, once party one has created it.
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            KeygenCheckpoint::Start => None,
+            KeygenCheckpoint::KeyGenFirst { id, .. } => Some(id),
+            KeygenCheckpoint::KeyGenSecond { id, .. } => Some(id),
+            KeygenCheckpoint::ChainCodeFirst { id, .. } => Some(id),
+            KeygenCheckpoint::Done(private_share) => Some(&private_share.id),
+        }
     }
-    let (id, kg_party_one_first_message): (String, party_one::KeyGenFirstMsg)  = match serde_json::from_str(server_reply.result.unwrap().as_str()){
-        Ok(s) => s,
-        Err(e) => return Err(format_err!("{}","Parse content erro from server!"))
-    };
-
-    let (kg_party_two_first_message, kg_ec_key_pair_party2) = MasterKey2::key_gen_first_message();
-
-    let ids_str = id.as_str();
-    
-    let key_gen_second_req = KeyGenSecondReq { 
-        id:ids_str.to_string(), 
-        d_log_proof:serde_json::to_string(&kg_party_two_first_message.d_log_proof).unwrap()
-    };
-    
-    let server_reply:ServerReply =  match requests::postb(client_shim, &format!("{}/second", KG_PATH_PRE), key_gen_second_req){
-        Some(s) => s,
-        None =>  return Err(format_err!("{}","Net error!"))
-    };
-
-    if server_reply.retCode != SUCCESS_CODE || server_reply.result == None {
-        return Err(format_err!("{}:{}",server_reply.retCode,server_reply.retMsg))
+
+    pub fn is_done(&self) -> bool {
+        match self {
+            KeygenCheckpoint::Done(_) => true,
+            _ => false,
+        }
     }
 
-    let kg_party_one_second_message: party1::KeyGenParty1Message2 = match serde_json::from_str(server_reply.result.unwrap().as_str()){
-        Ok(s) => s,
-        Err(e) => return Err(format_err!("{}","Parse content erro from server!"))
-    };
+    /// Path and body of the request that moves this checkpoint forward.
+    pub fn next_request(&self) -> Result<(String, serde_json::Value)> {
+        let (path, body) = match self {
+            // the JSON string "{}", the body keygen/first has always been sent
+            KeygenCheckpoint::Start => (
+                format!("{}/first", KG_PATH_PRE),
+                serde_json::Value::String("{}".to_string()),
+            ),
+            KeygenCheckpoint::KeyGenFirst { id, kg_party_two_first_message, .. } => (
+                format!("{}/second", KG_PATH_PRE),
+                serde_json::to_value(KeyGenSecondReq {
+                    id: id.to_string(),
+                    d_log_proof: serde_json::to_string(&kg_party_two_first_message.d_log_proof)?,
+                })?,
+            ),
+            KeygenCheckpoint::KeyGenSecond { id, .. } => (
+                format!("{}/chaincode/first", KG_PATH_PRE),
+                serde_json::to_value(ChainCodeFirstReq { id: id.to_string() })?,
+            ),
+            KeygenCheckpoint::ChainCodeFirst { id, cc_party_two_first_message, .. } => (
+                format!("{}/chaincode/second", KG_PATH_PRE),
+                serde_json::to_value(ChainCodeSecondReq {
+                    id: id.to_string(),
+                    d_log_proof: serde_json::to_string(&cc_party_two_first_message.d_log_proof)?,
+                })?,
+            ),
+            KeygenCheckpoint::Done(_) => {
+                return Err(ClientError::InvalidInput("keygen already finished".to_string()).into())
+            }
+        };
+        Ok((path, body))
+    }
 
-    let key_gen_second_message = MasterKey2::key_gen_second_message(
-        &kg_party_one_first_message,
-        &kg_party_one_second_message,
-        SALT_STRING,
-    );
+    /// Applies party one's reply to the request returned by `next_request`.
+    pub fn apply_reply(self, server_reply: ServerReply) -> Result<KeygenCheckpoint> {
+        match self {
+            KeygenCheckpoint::Start => {
+                let (id, kg_party_one_first_message): (String, party_one::KeyGenFirstMsg) = server_reply.into_result()?;
+
+                let (kg_party_two_first_message, kg_ec_key_pair_party2) = MasterKey2::key_gen_first_message();
+
+                Ok(KeygenCheckpoint::KeyGenFirst {
+                    id,
+                    kg_party_one_first_message,
+                    kg_party_two_first_message,
+                    kg_ec_key_pair_party2,
+                })
+            }
+            KeygenCheckpoint::KeyGenFirst { id, kg_party_one_first_message, kg_ec_key_pair_party2, .. } => {
+                let kg_party_one_second_message: party1::KeyGenParty1Message2 = server_reply.into_result()?;
+
+                // party two panics on a commitment that does not open, and
+                // reports every other failure as the same `()`, so the proofs
+                // are checked one by one before handing the message to it
+                let comm_witness = &kg_party_one_second_message.ecdh_second_message.comm_witness;
+                if !opens(&kg_party_one_first_message.pk_commitment, &comm_witness.public_share, &comm_witness.pk_commitment_blind_factor)
+                    || !opens(&kg_party_one_first_message.zk_pok_commitment, &comm_witness.d_log_proof.pk_t_rand_commitment, &comm_witness.zk_pok_blind_factor) {
+                    return Err(proof_failed(id, KeygenProof::Commitment))
+                }
+                if comm_witness.d_log_proof.pk != comm_witness.public_share || DLogProof::verify(&comm_witness.d_log_proof).is_err() {
+                    return Err(proof_failed(id, KeygenProof::DLog))
+                }
+                if kg_party_one_second_message.correct_key_proof.verify(&kg_party_one_second_message.ek, SALT_STRING).is_err() {
+                    return Err(proof_failed(id, KeygenProof::PaillierCorrectKey))
+                }
+
+                let key_gen_second_message = MasterKey2::key_gen_second_message(
+                    &kg_party_one_first_message,
+                    &kg_party_one_second_message,
+                    SALT_STRING,
+                );
+
+                // all that is left to fail is the range proof of the encrypted share
+                let (_, party_two_paillier) = match key_gen_second_message {
+                    Ok(s) => s,
+                    Err(_) => return Err(proof_failed(id, KeygenProof::PaillierRange))
+                };
+
+                Ok(KeygenCheckpoint::KeyGenSecond {
+                    id,
+                    kg_ec_key_pair_party2,
+                    kg_party_one_public_share: kg_party_one_second_message
+                        .ecdh_second_message
+                        .comm_witness
+                        .public_share,
+                    party_two_paillier,
+                })
+            }
+            KeygenCheckpoint::KeyGenSecond { id, kg_ec_key_pair_party2, kg_party_one_public_share, party_two_paillier } => {
+                let cc_party_one_first_message: Party1FirstMessage = server_reply.into_result()?;
+
+                let (cc_party_two_first_message, cc_ec_key_pair2) =
+                    chain_code::party2::ChainCode2::chain_code_first_message();
+
+                Ok(KeygenCheckpoint::ChainCodeFirst {
+                    id,
+                    kg_ec_key_pair_party2,
+                    kg_party_one_public_share,
+                    party_two_paillier,
+                    cc_party_one_first_message,
+                    cc_party_two_first_message,
+                    cc_ec_key_pair2,
+                })
+            }
+            KeygenCheckpoint::ChainCodeFirst {
+                id,
+                kg_ec_key_pair_party2,
+                kg_party_one_public_share,
+                party_two_paillier,
+                cc_party_one_first_message,
+                cc_ec_key_pair2,
+                ..
+            } => {
+                let cc_party_one_second_message: Party1SecondMessage<GE> = server_reply.into_result()?;
+
+                let cc_comm_witness = &cc_party_one_second_message.comm_witness;
+                if !opens(&cc_party_one_first_message.pk_commitment, &cc_comm_witness.public_share, &cc_comm_witness.pk_commitment_blind_factor)
+                    || !opens(&cc_party_one_first_message.zk_pok_commitment, &cc_comm_witness.d_log_proof.pk_t_rand_commitment, &cc_comm_witness.zk_pok_blind_factor) {
+                    return Err(proof_failed(id, KeygenProof::ChainCode))
+                }
+
+                let cc_party_two_second_message = chain_code::party2::ChainCode2::chain_code_second_message(
+                    &cc_party_one_first_message,
+                    &cc_party_one_second_message,
+                );
+
+                if cc_party_two_second_message.is_err() {
+                    return Err(proof_failed(id, KeygenProof::ChainCode))
+                }
+
+                let party2_cc = chain_code::party2::ChainCode2::compute_chain_code(
+                    &cc_ec_key_pair2,
+                    &cc_party_one_second_message.comm_witness.public_share,
+                )
+                    .chain_code;
+
+                let master_key = MasterKey2::set_master_key(
+                    &party2_cc,
+                    &kg_ec_key_pair_party2,
+                    &kg_party_one_public_share,
+                    &party_two_paillier,
+                );
+
+                Ok(KeygenCheckpoint::Done(PrivateShare { id, master_key }))
+            }
+            KeygenCheckpoint::Done(_) => {
+                Err(ClientError::InvalidInput("keygen already finished".to_string()).into())
+            }
+        }
+    }
+}
 
-    let (_, party_two_paillier) = key_gen_second_message.unwrap();
+/// Whether `blind_factor` opens `commitment` to the point `opening`.
+fn opens(commitment: &BigInt, opening: &GE, blind_factor: &BigInt) -> bool {
+    commitment == &HashCommitment::create_commitment_with_user_defined_randomness(
+        &opening.bytes_compressed_to_big_int(),
+        blind_factor,
+    )
+}
 
-    let chain_code_first_req = ChainCodeFirstReq { id:ids_str.to_string()  };
+fn proof_failed(id: String, proof: KeygenProof) -> failure::Error {
+    ClientError::KeygenProof(KeygenProofError { id, proof }).into()
+}
 
-    let server_reply:ServerReply = match requests::postb(client_shim,&format!("{}/chaincode/first", KG_PATH_PRE), chain_code_first_req){
-        Some(s) => s,
-        None =>  return Err(format_err!("{}","Net error!"))
-    };
-    
-    if server_reply.retCode != SUCCESS_CODE || server_reply.result == None{
-        return Err(format_err!("{}:{}",server_reply.retCode,server_reply.retMsg))
-    }
-    let cc_party_one_first_message: Party1FirstMessage = match serde_json::from_str(server_reply.result.unwrap().as_str()){
-        Ok(s) => s,
-        Err(e) => return Err(format_err!("{}","Parse content erro from server!"))
-    };
-
-    let (cc_party_two_first_message, cc_ec_key_pair2) =
-        chain_code::party2::ChainCode2::chain_code_first_message();
-
-    let chain_code_second_req = ChainCodeSecondReq{
-        id:ids_str.to_string(),
-        d_log_proof:serde_json::to_string(&cc_party_two_first_message.d_log_proof).unwrap()
-    };
-
-    let server_reply:ServerReply = match requests::postb(client_shim,&format!("{}/chaincode/second", KG_PATH_PRE),chain_code_second_req,){
-        Some(s) => s,
-        None =>  return Err(format_err!("{}","Net error!"))
-    };
-
-    if server_reply.retCode != SUCCESS_CODE || server_reply.result == None{
-        return Err(format_err!("{}:{}",server_reply.retCode,server_reply.retMsg))
+/// The session to abandon when `e` is a failed keygen proof.
+fn abandoned_session(e: &failure::Error) -> Option<&str> {
+    match e.downcast_ref::<ClientError>() {
+        Some(ClientError::KeygenProof(proof_error)) => Some(&proof_error.id),
+        _ => None,
     }
-    let cc_party_one_second_message: Party1SecondMessage<GE> = match serde_json::from_str(server_reply.result.unwrap().as_str()){
-        Ok(s) => s,
-        Err(e) => return Err(format_err!("{}","Parse content erro from server!"))
-    };
+}
 
+/// Performs the next round trip of a keygen, returning the new checkpoint.
+///
+/// When a proof from party one does not verify, the checkpoint is dropped,
+/// party one is asked to abandon the session and a `KeygenProofError` is
+/// returned; the app must discard its persisted checkpoint and start over.
+pub fn advance_keygen(client_shim: &ClientShim, checkpoint: KeygenCheckpoint) -> Result<KeygenCheckpoint> {
+    let (path, body) = checkpoint.next_request()?;
+    let server_reply: ServerReply = requests::postb(client_shim, &path, body)?;
+    checkpoint.apply_reply(server_reply).map_err(|e| {
+        if let Some(id) = abandoned_session(&e) {
+            if let Err(abandon_error) = abandon_keygen(client_shim, id) {
+                warn!("abandoning keygen {} failed: {}", id, abandon_error);
+            }
+        }
+        e
+    })
+}
 
-    let cc_party_two_second_message = chain_code::party2::ChainCode2::chain_code_second_message(
-        &cc_party_one_first_message,
-        &cc_party_one_second_message,
-    );
+/// Asks party one to drop the unfinished keygen session `id`.
+pub fn abandon_keygen(client_shim: &ClientShim, id: &str) -> Result<()> {
+    let path = format!("{}/abandon", KG_PATH_PRE);
+    let server_reply: ServerReply = requests::postb(client_shim, &path, KeyGenAbandonReq { id: id.to_string() })?;
+    let _: String = server_reply.into_result()?;
+    Ok(())
+}
 
-    assert!(cc_party_two_second_message.is_ok());
+/// Runs keygen to completion from `checkpoint`, calling `on_checkpoint` after
+/// every completed round trip so the caller can persist the progress.
+pub fn resume_master_key<F>(
+    client_shim: &ClientShim,
+    checkpoint: KeygenCheckpoint,
+    mut on_checkpoint: F,
+) -> Result<PrivateShare>
+    where F: FnMut(&KeygenCheckpoint)
+{
+    let start = Instant::now();
 
-    let party2_cc = chain_code::party2::ChainCode2::compute_chain_code(
-        &cc_ec_key_pair2,
-        &cc_party_one_second_message.comm_witness.public_share,
-    )
-        .chain_code;
+    let mut checkpoint = checkpoint;
+    loop {
+        if let KeygenCheckpoint::Done(private_share) = checkpoint {
+            info!("(keygen {}, took: {})", private_share.id, TimeFormat(start.elapsed()));
+            return Ok(private_share)
+        }
+        checkpoint = advance_keygen(client_shim, checkpoint)?;
+        on_checkpoint(&checkpoint);
+    }
+}
+
+pub fn get_master_key(client_shim: &ClientShim) -> Result<PrivateShare> {
+    resume_master_key(client_shim, KeygenCheckpoint::Start, |_| {})
+}
+
+/// Async flavour of `advance_keygen`.
+#[cfg(feature = "async")]
+pub async fn advance_keygen_async(client_shim: &AsyncClientShim, checkpoint: KeygenCheckpoint) -> Result<KeygenCheckpoint> {
+    let (path, body) = checkpoint.next_request()?;
+    let server_reply: ServerReply = async_requests::postb(client_shim, &path, body).await?;
+    match checkpoint.apply_reply(server_reply) {
+        Ok(checkpoint) => Ok(checkpoint),
+        Err(e) => {
+            if let Some(id) = abandoned_session(&e) {
+                if let Err(abandon_error) = abandon_keygen_async(client_shim, id).await {
+                    warn!("abandoning keygen {} failed: {}", id, abandon_error);
+                }
+            }
+            Err(e)
+        }
+    }
+}
 
-    let master_key = MasterKey2::set_master_key(
-        &party2_cc,
-        &kg_ec_key_pair_party2,
-        &kg_party_one_second_message
-            .ecdh_second_message
-            .comm_witness
-            .public_share,
-        &party_two_paillier,
-    );
+/// Async flavour of `abandon_keygen`.
+#[cfg(feature = "async")]
+pub async fn abandon_keygen_async(client_shim: &AsyncClientShim, id: &str) -> Result<()> {
+    let path = format!("{}/abandon", KG_PATH_PRE);
+    let server_reply: ServerReply = async_requests::postb(client_shim, &path, KeyGenAbandonReq { id: id.to_string() }).await?;
+    let _: String = server_reply.into_result()?;
+    Ok(())
+}
+
+/// Async flavour of `get_master_key`, sharing the protocol steps with it.
+#[cfg(feature = "async")]
+pub async fn get_master_key_async(client_shim: &AsyncClientShim) -> Result<PrivateShare> {
+    let start = Instant::now();
 
-    Ok(PrivateShare { id, master_key })
+    let mut checkpoint = KeygenCheckpoint::Start;
+    loop {
+        if let KeygenCheckpoint::Done(private_share) = checkpoint {
+            info!("(keygen {}, took: {})", private_share.id, TimeFormat(start.elapsed()));
+            return Ok(private_share)
+        }
+        checkpoint = advance_keygen_async(client_shim, checkpoint).await?;
+    }
 }
 
 #[no_mangle]
@@ -160,59 +382,131 @@ pub extern "C" fn get_client_master_key(
     c_endpoint: *const c_char,
     c_auth_token: *const c_char,
 ) -> *mut c_char {
-    let raw_endpoint = unsafe { CStr::from_ptr(c_endpoint) };
-    let endpoint = match raw_endpoint.to_str() {
-        Ok(s) => s,
-        Err(e) => {
-            return error_to_c_string(format_err!(
-                "Error while decoding raw endpoint: {}",
-                e
-            ))
+    catch_panic(|| {
+        let raw_endpoint = unsafe { CStr::from_ptr(c_endpoint) };
+        let endpoint = match raw_endpoint.to_str() {
+            Ok(s) => s,
+            Err(e) => {
+                return error_to_c_string(ClientError::InvalidInput(format!(
+                    "Error while decoding raw endpoint: {}",
+                    e
+                )).into())
+            }
+        };
+
+        let raw_auth_token = unsafe { CStr::from_ptr(c_auth_token) };
+        let auth_token = match raw_auth_token.to_str() {
+            Ok(s) => s,
+            Err(e) => {
+                return error_to_c_string(ClientError::InvalidInput(format!(
+                    "Error while decoding auth token: {}",
+                    e
+                )).into())
+            }
+        };
+
+        let client_shim = ClientShim::new(endpoint.to_string(), Some(auth_token.to_string()));
+
+        let private_share = get_master_key(&client_shim);
+
+        match private_share {
+            Ok(result) => return success_to_c_string(result),
+            Err(e) => return error_to_c_string(e),
         }
-    };
+    })
+}
 
-    let raw_auth_token = unsafe { CStr::from_ptr(c_auth_token) };
-    let auth_token = match raw_auth_token.to_str() {
-        Ok(s) => s,
-        Err(e) => {
-            return error_to_c_string(format_err!(
-                "Error while decoding auth token: {}",
-                e
-            ))
+/// Same as `get_client_master_key`, using a client created by `new_client_shim`.
+#[no_mangle]
+pub extern "C" fn get_client_master_key_with_client(
+    c_client_shim: *const ClientShim,
+) -> *mut c_char {
+    catch_panic(|| {
+        let client_shim = match unsafe { c_client_shim.as_ref() } {
+            Some(c) => c,
+            None => return error_to_c_string(ClientError::InvalidInput("client_shim is null".to_string()).into())
+        };
+
+        match get_master_key(client_shim) {
+            Ok(result) => return success_to_c_string(result),
+            Err(e) => return error_to_c_string(e),
         }
-    };
-
-    let client_shim = ClientShim::new(endpoint.to_string(), Some(auth_token.to_string()));
+    })
+}
 
-    let private_share = get_master_key(&client_shim);
 
-    match private_share {
-        Ok(result) => return success_to_c_string(result),
-        Err(e) => return error_to_c_string(format_err!("{}", e)),
-    }
+/// Performs one keygen round trip starting from `c_checkpoint_json` (an empty
+/// string starts a new keygen) and returns the next `KeygenCheckpoint`.
+///
+/// The app persists every returned checkpoint and calls again until its
+/// `step` is `done`; after a crash it resumes from the last persisted one.
+/// A `ret_code` of 10104004 means party one sent a bad proof: the session is
+/// abandoned and the persisted checkpoint must be deleted.
+#[no_mangle]
+pub extern "C" fn get_client_master_key_step(
+    c_endpoint: *const c_char,
+    c_auth_token: *const c_char,
+    c_checkpoint_json: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_endpoint = unsafe { CStr::from_ptr(c_endpoint) };
+        let endpoint = match raw_endpoint.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw endpoint failed: {}", e)).into())
+        };
+
+        let raw_auth_token = unsafe { CStr::from_ptr(c_auth_token) };
+        let auth_token = match raw_auth_token.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw auth_token failed: {}", e)).into())
+        };
+
+        let raw_checkpoint_json = unsafe { CStr::from_ptr(c_checkpoint_json) };
+        let checkpoint_json = match raw_checkpoint_json.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw checkpoint_json failed: {}", e)).into())
+        };
+
+        let checkpoint: KeygenCheckpoint = if checkpoint_json.trim().is_empty() {
+            KeygenCheckpoint::Start
+        } else {
+            match serde_json::from_str(checkpoint_json) {
+                Ok(c) => c,
+                Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding checkpoint_json failed: {}", e)).into())
+            }
+        };
+
+        let client_shim = ClientShim::new(endpoint.to_string(), Some(auth_token.to_string()));
+
+        match advance_keygen(&client_shim, checkpoint) {
+            Ok(result) => return success_to_c_string(result),
+            Err(e) => return error_to_c_string(e),
+        }
+    })
 }
 
-
 #[no_mangle]
 pub extern "C" fn get_public_share_key(
     c_party2_public_key_json:*const c_char
 ) -> *mut c_char {
-
-    let raw_party2_public_key_json = unsafe { CStr::from_ptr(c_party2_public_key_json) };
-    let party2_public_key_json = match raw_party2_public_key_json.to_str() {
-        Ok(s) => s,
-        Err(e) => return error_to_c_string(format_err!("decoding raw private_share_json failed: {}", e))
-    };
-
-    let party2_public:Party2Public = match serde_json::from_str(party2_public_key_json){
-        Ok(s) => s,
-        Err(e) => {
-            return error_to_c_string(format_err!("decoding private_share_json to PrivateShare failed: {}", e))
-        }
-    };
-
-    let pk = party2_public.q.get_element();
-    return success_to_c_string(pk)
+    catch_panic(|| {
+
+        let raw_party2_public_key_json = unsafe { CStr::from_ptr(c_party2_public_key_json) };
+        let party2_public_key_json = match raw_party2_public_key_json.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw private_share_json failed: {}", e)).into())
+        };
+
+        let party2_public:Party2Public = match serde_json::from_str(party2_public_key_json){
+            Ok(s) => s,
+            Err(e) => {
+                return error_to_c_string(ClientError::KeyFormat(format!("decoding private_share_json to PrivateShare failed: {}", e)).into())
+            }
+        };
+
+        let pk = party2_public.q.get_element();
+        return success_to_c_string(pk)
+    })
 }
 
 #[no_mangle]
@@ -221,30 +515,74 @@ pub extern "C" fn get_public_share_key_with_derive(
     c_x_pos: i32,//coinType
     c_y_pos: i32,//account:1,2
 ) -> *mut c_char {
-    let raw_master_key_json = unsafe { CStr::from_ptr(c_master_key_json) };
-    let master_key_json = match raw_master_key_json.to_str() {
-        Ok(s) => s,
-        Err(e) => return error_to_c_string(format_err!("decoding raw master_key_json failed: {}", e))
-    };
-
-    let x: BigInt = BigInt::from(c_x_pos);
-    let y: BigInt = BigInt::from(c_y_pos);
-
-    let mk: MasterKey2 = match serde_json::from_str(master_key_json){
-        Ok(s) => s,
-        Err(e) => {
-            return error_to_c_string(format_err!("decoding master_key_json to MasterKey2 failed: {}", e))
-        }
-    };
-
-    let mk_child: MasterKey2 = mk.get_child(vec![x.clone(), y.clone()]);
+    catch_panic(|| {
+        let raw_master_key_json = unsafe { CStr::from_ptr(c_master_key_json) };
+        let master_key_json = match raw_master_key_json.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw master_key_json failed: {}", e)).into())
+        };
+
+        let x: BigInt = BigInt::from(c_x_pos);
+        let y: BigInt = BigInt::from(c_y_pos);
+
+        let mk: MasterKey2 = match serde_json::from_str(master_key_json){
+            Ok(s) => s,
+            Err(e) => {
+                return error_to_c_string(ClientError::KeyFormat(format!("decoding master_key_json to MasterKey2 failed: {}", e)).into())
+            }
+        };
+
+        let mk_child: MasterKey2 = mk.get_child(vec![x.clone(), y.clone()]);
+
+        let pk = mk_child.public.q.get_element();
+        return success_to_c_string(pk)
+    })
+}
 
-    let pk = mk_child.public.q.get_element();
-    return success_to_c_string(pk)
+#[no_mangle]
+pub extern "C" fn get_public_share_key_with_path(
+    c_master_key_json: *const c_char,//masterkey
+    c_path: *const c_char,//m/44/60/0/0/5
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_master_key_json = unsafe { CStr::from_ptr(c_master_key_json) };
+        let master_key_json = match raw_master_key_json.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw master_key_json failed: {}", e)).into())
+        };
+
+        let raw_path = unsafe { CStr::from_ptr(c_path) };
+        let path: DerivationPath = match raw_path.to_str() {
+            Ok(s) => match s.parse() {
+                Ok(p) => p,
+                Err(e) => return error_to_c_string(failure::Error::from(e))
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw path failed: {}", e)).into())
+        };
+
+        let mk: MasterKey2 = match serde_json::from_str(master_key_json){
+            Ok(s) => s,
+            Err(e) => {
+                return error_to_c_string(ClientError::KeyFormat(format!("decoding master_key_json to MasterKey2 failed: {}", e)).into())
+            }
+        };
+
+        let mk_child: MasterKey2 = super::key_derive::derive_child(&mk, &path);
+
+        let pk = mk_child.public.q.get_element();
+        return success_to_c_string(pk)
+    })
 }
 
 #[test]
 fn test_public_share_key() {
     let fs = std::fs::read_to_string("testAssets/party2public.json").unwrap();
     let rt = get_public_share_key(CString::new(fs).unwrap().into_raw());
+    let c_result = unsafe { CString::from_raw(rt) };
+    let c_result: serde_json::Value = serde_json::from_str(c_result.to_str().unwrap()).unwrap();
+    assert_eq!(c_result["ret_code"], 0);
+
+    let expected = super::key_derive::vector_point(&super::key_derive::derive_vectors()["party2_public"]["q"]);
+    let pk: serde_json::Value = serde_json::from_str(c_result["result"].as_str().unwrap()).unwrap();
+    assert_eq!(pk, serde_json::to_value(expected.get_element()).unwrap());
 }
\ No newline at end of file
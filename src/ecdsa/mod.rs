@@ -1,12 +1,30 @@
 pub use keygen::get_master_key;
+#[cfg(feature = "async")]
+pub use keygen::get_master_key_async;
+pub use path::DerivationPath;
 pub use types::PrivateShare;
 
+use crate::error::ClientError;
+use crate::utilities::SUCCESS_CODE;
+
+use super::Result;
+
 pub mod test;
+pub mod batch;
+pub mod client;
 pub mod keygen;
 pub mod types;
 pub mod sign;
+pub mod signature;
+pub mod xpub;
 pub mod free;
 pub mod key_derive;
+pub mod envelope;
+pub mod path;
+pub mod presign;
+pub mod rotate;
+#[cfg(feature = "local-party-one")]
+pub mod server;
 
 #[derive(Serialize, Deserialize)]
 pub struct ServerReply{
@@ -15,3 +33,21 @@ pub struct ServerReply{
     result : Option<String>
 }
 
+impl ServerReply {
+    /// Checks `retCode` and decodes the `result` payload into `V`.
+    pub fn into_result<V>(self) -> Result<V>
+        where V: serde::de::DeserializeOwned
+    {
+        if self.retCode != SUCCESS_CODE {
+            return Err(ClientError::ServerRejected { code: self.retCode, msg: self.retMsg }.into())
+        }
+        let result = match self.result {
+            Some(s) => s,
+            None => return Err(ClientError::MalformedResponse("missing result".to_string()).into())
+        };
+        match serde_json::from_str(result.as_str()) {
+            Ok(v) => Ok(v),
+            Err(e) => Err(ClientError::MalformedResponse(format!("{}", e)).into())
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use std::fmt;
+use std::str::FromStr;
+
+use curv::BigInt;
+
+use crate::error::ClientError;
+
+/// A non-hardened derivation path such as `m/44/60/0/0/5`.
+///
+/// Two-party derivation (`MasterKey2::get_child`) only supports public
+/// derivation, so hardened markers (`'` or `h`) are rejected. Every index is
+/// a `u32` and at least one index is required.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DerivationPath {
+    indices: Vec<u32>,
+}
+
+impl DerivationPath {
+    pub fn new(indices: Vec<u32>) -> Result<DerivationPath, ClientError> {
+        if indices.is_empty() {
+            return Err(ClientError::InvalidInput("derivation path needs at least one index".to_string()))
+        }
+        Ok(DerivationPath { indices })
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    /// The path in the form expected by `MasterKey2::get_child`.
+    pub fn to_bigints(&self) -> Vec<BigInt> {
+        self.indices.iter().map(|i| BigInt::from(*i as u64)).collect()
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = ClientError;
+
+    fn from_str(s: &str) -> Result<DerivationPath, ClientError> {
+        let s = s.trim();
+        let rest = if s == "m" {
+            ""
+        } else if s.starts_with("m/") {
+            &s[2..]
+        } else {
+            s
+        };
+
+        let mut indices = Vec::new();
+        let components = if rest.is_empty() { Vec::new() } else { rest.split('/').collect() };
+        for component in components {
+            if component.ends_with('\'') || component.ends_with('h') || component.ends_with('H') {
+                return Err(ClientError::InvalidInput(format!("hardened index {} is not supported in path {}", component, s)))
+            }
+            if component.is_empty() || !component.chars().all(|c| c.is_ascii_digit()) {
+                return Err(ClientError::InvalidInput(format!("invalid index '{}' in path {}", component, s)))
+            }
+            match component.parse::<u32>() {
+                Ok(i) => indices.push(i),
+                Err(_) => return Err(ClientError::InvalidInput(format!("index {} out of range in path {}", component, s)))
+            }
+        }
+
+        DerivationPath::new(indices)
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "m")?;
+        for i in &self.indices {
+            write!(f, "/{}", i)?;
+        }
+        Ok(())
+    }
+}
+
+impl serde::Serialize for DerivationPath {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for DerivationPath {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<DerivationPath, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[test]
+fn test_derivation_path() {
+    let path: DerivationPath = "m/44/60/0/0/5".parse().unwrap();
+    assert_eq!(path.indices(), &[44, 60, 0, 0, 5]);
+    assert_eq!(path.to_string(), "m/44/60/0/0/5");
+    assert_eq!("60/0".parse::<DerivationPath>().unwrap().indices(), &[60, 0]);
+    assert_eq!("m/4294967295".parse::<DerivationPath>().unwrap().indices(), &[u32::MAX]);
+
+    for bad in &["m", "", "m/44'/60", "m/44h", "m/-1", "m/4294967296", "m//1", "m/1/", "m/abc"] {
+        assert!(bad.parse::<DerivationPath>().is_err(), "{}", bad);
+    }
+}
@@ -0,0 +1,530 @@
+use std::collections::VecDeque;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+use curv::BigInt;
+use kms::ecdsa::two_party::MasterKey2;
+use multi_party_ecdsa::protocols::two_party_ecdsa::lindell_2017::party_one;
+
+use crate::error::ClientError;
+use crate::utilities::{catch_panic, error_to_c_string, error_with_result_to_c_string, success_to_c_string};
+
+use super::super::ClientShim;
+use super::super::Result;
+use super::super::utilities::requests;
+use super::batch::SIGN_BATCH_MAX;
+use super::path::DerivationPath;
+use super::sign::{normalize_s, sign_first_request, sign_second_request_with, verify_signature, SignFirstState, SIGN_PATH_PRE};
+use super::ServerReply;
+
+/// How many consumed ids a pool remembers; older ones are forgotten, party
+/// one refuses them anyway.
+pub const CONSUMED_MAX: usize = 1024;
+
+#[derive(Serialize, Deserialize)]
+pub struct PresignRequest {
+    pub id: String,
+    pub ephKeyGenFirstMsgs: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PresignReply {
+    pub presignId: String,
+    pub ephKeyGenFirstMsg: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SignPresignedRequest {
+    pub id: String,
+    pub presignId: String,
+    pub signSecondMsgReq: String,
+}
+
+/// The first sign round, done ahead of time. It holds party two's ephemeral
+/// secret and must sign at most one message: its pool refuses a
+/// `presign_id` it has signed with before, so it is neither `Clone` nor put
+/// back once taken.
+#[derive(Serialize, Deserialize)]
+pub struct Presignature {
+    pub id: String,
+    pub presign_id: String,
+    state: SignFirstState,
+    party_one_first_message: party_one::EphKeyGenFirstMsg,
+}
+
+/// Presignatures of one key. The JSON form contains ephemeral secrets and
+/// must be stored like the `PrivateShare`.
+#[derive(Serialize, Deserialize)]
+pub struct PresignaturePool {
+    pub id: String,
+    presignatures: VecDeque<Presignature>,
+    /// Ids of the presignatures last signed with or discarded, oldest first,
+    /// at most `CONSUMED_MAX`.
+    #[serde(default)]
+    consumed: VecDeque<String>,
+}
+
+impl PresignaturePool {
+    pub fn new(id: &str) -> PresignaturePool {
+        PresignaturePool {
+            id: id.to_string(),
+            presignatures: VecDeque::new(),
+            consumed: VecDeque::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.presignatures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.presignatures.is_empty()
+    }
+
+    /// Runs the first sign round for `count` more presignatures.
+    pub fn fill(&mut self, client_shim: &ClientShim, count: usize) -> Result<()> {
+        let mut remaining = count;
+        while remaining > 0 {
+            let n = remaining.min(SIGN_BATCH_MAX);
+            let mut states = Vec::with_capacity(n);
+            let mut first_msgs = Vec::with_capacity(n);
+            for _ in 0..n {
+                let (state, sign_first_req) = sign_first_request(&self.id)?;
+                states.push(state);
+                first_msgs.push(sign_first_req.ephKeyGenFirstMsg);
+            }
+
+            let presign_req = PresignRequest { id: self.id.clone(), ephKeyGenFirstMsgs: first_msgs };
+            let server_reply: ServerReply = requests::postb(client_shim, &format!("{}/presign", SIGN_PATH_PRE), &presign_req)?;
+            let replies: Vec<PresignReply> = server_reply.into_result()?;
+            if replies.len() != n {
+                return Err(ClientError::MalformedResponse(format!("{} presignatures for {} requested", replies.len(), n)).into())
+            }
+
+            for (state, reply) in states.into_iter().zip(replies) {
+                if self.is_consumed(&reply.presignId) || self.presignatures.iter().any(|p| p.presign_id == reply.presignId) {
+                    return Err(ClientError::MalformedResponse(format!("presignature {} handed out twice", reply.presignId)).into())
+                }
+                let party_one_first_message: party_one::EphKeyGenFirstMsg = match serde_json::from_str(&reply.ephKeyGenFirstMsg) {
+                    Ok(m) => m,
+                    Err(e) => return Err(ClientError::MalformedResponse(format!("{}", e)).into())
+                };
+                self.presignatures.push_back(Presignature {
+                    id: self.id.clone(),
+                    presign_id: reply.presignId,
+                    state,
+                    party_one_first_message,
+                });
+            }
+            remaining -= n;
+        }
+        Ok(())
+    }
+
+    /// Removes the oldest presignature. It may still sign once, through
+    /// `sign_with` on this pool.
+    pub fn take(&mut self) -> Option<Presignature> {
+        self.presignatures.pop_front()
+    }
+
+    /// Removes the oldest presignature without signing, e.g. after signing
+    /// with it failed, and returns its id.
+    pub fn discard(&mut self) -> Option<String> {
+        let presignature = self.presignatures.pop_front()?;
+        self.consume(&presignature.presign_id);
+        Some(presignature.presign_id)
+    }
+
+    pub fn is_consumed(&self, presign_id: &str) -> bool {
+        self.consumed.iter().any(|c| c == presign_id)
+    }
+
+    /// Records `presign_id` as spent, false if it already was.
+    fn consume(&mut self, presign_id: &str) -> bool {
+        if self.is_consumed(presign_id) {
+            return false
+        }
+        self.consumed.push_back(presign_id.to_string());
+        while self.consumed.len() > CONSUMED_MAX {
+            self.consumed.pop_front();
+        }
+        true
+    }
+
+    /// Signs with the oldest presignature, see `sign_with`.
+    pub fn sign(
+        &mut self,
+        client_shim: &ClientShim,
+        message: BigInt,
+        mk: &MasterKey2,
+        path: &DerivationPath,
+    ) -> Result<party_one::SignatureRecid> {
+        let presignature = match self.take() {
+            Some(p) => p,
+            None => return Err(ClientError::InvalidInput(format!("no presignature left for {}", self.id)).into())
+        };
+        self.sign_with(client_shim, presignature, message, mk, path)
+    }
+
+    /// Signs `message` with `mk`, the child key already derived at `path`, in
+    /// a single `/sign/presigned/second` round trip.
+    ///
+    /// `presignature` is marked consumed before anything is sent, whether or
+    /// not signing succeeds, and a presignature already consumed by this pool
+    /// is refused: two signatures under one ephemeral key reveal the share.
+    pub fn sign_with(
+        &mut self,
+        client_shim: &ClientShim,
+        presignature: Presignature,
+        message: BigInt,
+        mk: &MasterKey2,
+        path: &DerivationPath,
+    ) -> Result<party_one::SignatureRecid> {
+        if presignature.id != self.id {
+            return Err(ClientError::InvalidInput(format!("presignature belongs to {}, not {}", presignature.id, self.id)).into())
+        }
+        if !self.consume(&presignature.presign_id) {
+            return Err(ClientError::InvalidInput(format!("presignature {} already used", presignature.presign_id)).into())
+        }
+        sign_presigned(client_shim, presignature, message, mk, path)
+    }
+}
+
+fn sign_presigned(
+    client_shim: &ClientShim,
+    presignature: Presignature,
+    message: BigInt,
+    mk: &MasterKey2,
+    path: &DerivationPath,
+) -> Result<party_one::SignatureRecid> {
+    let Presignature { id, presign_id, state, party_one_first_message } = presignature;
+
+    let sign_second_req = sign_second_request_with(mk, state, &party_one_first_message, message.clone(), path.to_bigints(), &id)?;
+    let sign_presigned_req = SignPresignedRequest {
+        id,
+        presignId: presign_id,
+        signSecondMsgReq: sign_second_req.signSecondMsgReq,
+    };
+
+    let server_reply: ServerReply = requests::postb(client_shim, &format!("{}/presigned/second", SIGN_PATH_PRE), &sign_presigned_req)?;
+    let signature: party_one::SignatureRecid = server_reply.into_result()?;
+
+    verify_signature(&signature, &mk.public.q, &message)?;
+
+    Ok(normalize_s(signature))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DiscardedPresignature {
+    presign_id: Option<String>,
+    pool: PresignaturePool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PresignedSignature {
+    /// Absent when signing failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<party_one::SignatureRecid>,
+    pool: PresignaturePool,
+}
+
+fn decode_pool(raw_pool_json: &CStr) -> Result<PresignaturePool> {
+    match raw_pool_json.to_str() {
+        Ok(s) => match serde_json::from_str(s) {
+            Ok(p) => Ok(p),
+            Err(e) => Err(ClientError::InvalidInput(format!("decoding pool_json failed: {}", e)).into())
+        },
+        Err(e) => Err(ClientError::InvalidInput(format!("decoding raw pool_json failed: {}", e)).into())
+    }
+}
+
+/// Adds `c_count` presignatures to the pool JSON `c_pool_json` (empty for a
+/// new pool of key `c_id`) and returns the new pool.
+#[no_mangle]
+pub extern "C" fn presign_pool_fill(
+    c_endpoint: *const c_char,
+    c_auth_token: *const c_char,
+    c_pool_json: *const c_char,
+    c_id: *const c_char,
+    c_count: i32,
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_endpoint = unsafe { CStr::from_ptr(c_endpoint) };
+        let endpoint = match raw_endpoint.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw endpoint failed: {}", e)).into())
+        };
+
+        let raw_auth_token = unsafe { CStr::from_ptr(c_auth_token) };
+        let auth_token = match raw_auth_token.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw auth_token failed: {}", e)).into())
+        };
+
+        let raw_id = unsafe { CStr::from_ptr(c_id) };
+        let id = match raw_id.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw id failed: {}", e)).into())
+        };
+
+        let raw_pool_json = unsafe { CStr::from_ptr(c_pool_json) };
+        let mut pool = match raw_pool_json.to_bytes() {
+            b"" => PresignaturePool::new(id),
+            _ => match decode_pool(raw_pool_json) {
+                Ok(p) => p,
+                Err(e) => return error_to_c_string(e)
+            }
+        };
+
+        if pool.id != id {
+            return error_to_c_string(ClientError::InvalidInput(format!("pool belongs to {}, not {}", pool.id, id)).into())
+        }
+        if c_count < 0 {
+            return error_to_c_string(ClientError::InvalidInput(format!("invalid count {}", c_count)).into())
+        }
+
+        let client_shim = ClientShim::new(endpoint.to_string(), Some(auth_token.to_string()));
+
+        match pool.fill(&client_shim, c_count as usize) {
+            Ok(()) => success_to_c_string(pool),
+            Err(e) => {
+                error!("filling presignature pool failed: {}", e);
+                error_to_c_string(e)
+            }
+        }
+    })
+}
+
+/// Drops the oldest presignature of `c_pool_json` unused and returns the new
+/// pool.
+#[no_mangle]
+pub extern "C" fn presign_pool_discard(
+    c_pool_json: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_pool_json = unsafe { CStr::from_ptr(c_pool_json) };
+        let mut pool = match decode_pool(raw_pool_json) {
+            Ok(p) => p,
+            Err(e) => return error_to_c_string(e)
+        };
+
+        let presign_id = pool.discard();
+        success_to_c_string(DiscardedPresignature { presign_id, pool })
+    })
+}
+
+/// Signs with the oldest presignature of `c_pool_json` and the child key at
+/// `c_path`; only the second sign round trip is done online.
+///
+/// Returns the signature and the pool without that presignature; store the
+/// pool in place of the one passed in. The presignature is spent even when
+/// signing fails, so such an error carries the new pool in `result` too;
+/// an error with an empty `result` left the pool unchanged.
+#[no_mangle]
+pub extern "C" fn sign_message_presigned(
+    c_endpoint: *const c_char,
+    c_auth_token: *const c_char,
+    c_pool_json: *const c_char,
+    c_message_le_hex: *const c_char,
+    c_master_key_json: *const c_char,//masterkey
+    c_path: *const c_char,//m/44/60/0/0/5
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_endpoint = unsafe { CStr::from_ptr(c_endpoint) };
+        let endpoint = match raw_endpoint.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw endpoint failed: {}", e)).into())
+        };
+
+        let raw_auth_token = unsafe { CStr::from_ptr(c_auth_token) };
+        let auth_token = match raw_auth_token.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw auth_token failed: {}", e)).into())
+        };
+
+        let raw_pool_json = unsafe { CStr::from_ptr(c_pool_json) };
+        let mut pool = match decode_pool(raw_pool_json) {
+            Ok(p) => p,
+            Err(e) => return error_to_c_string(e)
+        };
+
+        let raw_message_hex = unsafe { CStr::from_ptr(c_message_le_hex) };
+        let message: BigInt = match raw_message_hex.to_str() {
+            Ok(s) => match serde_json::from_str(s) {
+                Ok(m) => m,
+                Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding message_hex failed: {}", e)).into())
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw message_hex failed: {}", e)).into())
+        };
+
+        let raw_master_key_json = unsafe { CStr::from_ptr(c_master_key_json) };
+        let master_key_json = match raw_master_key_json.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw master_key_json failed: {}", e)).into())
+        };
+
+        let raw_path = unsafe { CStr::from_ptr(c_path) };
+        let path: DerivationPath = match raw_path.to_str() {
+            Ok(s) => match s.parse() {
+                Ok(p) => p,
+                Err(e) => return error_to_c_string(failure::Error::from(e))
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw path failed: {}", e)).into())
+        };
+
+        let mk: MasterKey2 = match serde_json::from_str(master_key_json){
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::KeyFormat(format!("decoding master_key_json to MasterKey2 failed: {}", e)).into())
+        };
+
+        let mk_child = mk.get_child(path.to_bigints());
+
+        let client_shim = ClientShim::new(endpoint.to_string(), Some(auth_token.to_string()));
+
+        match pool.sign(&client_shim, message, &mk_child, &path) {
+            Ok(signature) => success_to_c_string(PresignedSignature { signature: Some(signature), pool }),
+            Err(e) => {
+                error!("presigned signing failed: {}", e);
+                error_with_result_to_c_string(e, PresignedSignature { signature: None, pool })
+            }
+        }
+    })
+}
+
+#[test]
+fn test_presignature_replay_refused() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // party one stand-in that only counts requests; none of them succeeds
+    struct CountingTransport(Arc<AtomicUsize>);
+
+    impl crate::Transport for CountingTransport {
+        fn post(&self, _path: &str, _body: String) -> Result<String> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Err(ClientError::Network("connection reset".to_string()).into())
+        }
+    }
+
+    let fs = std::fs::read_to_string("testAssets/mk.json").unwrap();
+    let private_share: serde_json::Value = serde_json::from_str(&fs).unwrap();
+    let mk: MasterKey2 = serde_json::from_value(private_share["master_key"].clone()).unwrap();
+    let path: DerivationPath = "m/44/60/0/0/1".parse().unwrap();
+    let child = mk.get_child(path.to_bigints());
+
+    let mut pool = test_pool("replay", 2);
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let client_shim = ClientShim::with_transport(Box::new(CountingTransport(calls.clone())));
+
+    let presignature = pool.take().unwrap();
+    let copy: Presignature = serde_json::from_str(&serde_json::to_string(&presignature).unwrap()).unwrap();
+
+    // a failed attempt still spends the presignature
+    assert!(pool.sign_with(&client_shim, presignature, BigInt::from(1234), &child, &path).is_err());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert!(pool.is_consumed("presign-0"));
+
+    // the copy is refused before anything is sent, also by the persisted pool
+    let mut pool: PresignaturePool = serde_json::from_str(&serde_json::to_string(&pool).unwrap()).unwrap();
+    let err = pool.sign_with(&client_shim, copy, BigInt::from(4321), &child, &path).unwrap_err();
+    assert_eq!(crate::error::error_code(&err), crate::error::INVALID_INPUT_CODE);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    assert_eq!(pool.discard(), Some("presign-1".to_string()));
+    assert!(pool.sign(&client_shim, BigInt::from(1), &child, &path).is_err());
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[cfg(test)]
+fn test_pool(id: &str, count: usize) -> PresignaturePool {
+    use kms::ecdsa::two_party::MasterKey1;
+
+    let mut pool = PresignaturePool::new(id);
+    for i in 0..count {
+        let (state, _) = sign_first_request(&pool.id).unwrap();
+        let (party_one_first_message, _) = MasterKey1::sign_first_message();
+        pool.presignatures.push_back(Presignature {
+            id: pool.id.clone(),
+            presign_id: format!("presign-{}", i),
+            state,
+            party_one_first_message,
+        });
+    }
+    pool
+}
+
+#[test]
+fn test_consumed_ids_capped() {
+    let mut pool = test_pool("capped", 0);
+    for i in 0..CONSUMED_MAX + 1 {
+        assert!(pool.consume(&format!("presign-{}", i)));
+    }
+    assert_eq!(pool.consumed.len(), CONSUMED_MAX);
+    assert!(!pool.is_consumed("presign-0"));
+    assert!(!pool.consume(&format!("presign-{}", CONSUMED_MAX)));
+}
+
+#[test]
+fn test_sign_message_presigned_error_returns_pool() {
+    use std::ffi::CString;
+
+    let fs = std::fs::read_to_string("testAssets/mk.json").unwrap();
+    let private_share: serde_json::Value = serde_json::from_str(&fs).unwrap();
+    let pool = test_pool("unreachable", 1);
+
+    // nothing listens there, the presignature is spent all the same
+    let rt = sign_message_presigned(
+        CString::new("http://127.0.0.1:1").unwrap().into_raw(),
+        CString::new("token").unwrap().into_raw(),
+        CString::new(serde_json::to_string(&pool).unwrap()).unwrap().into_raw(),
+        CString::new("\"4d2\"").unwrap().into_raw(),
+        CString::new(private_share["master_key"].to_string()).unwrap().into_raw(),
+        CString::new("m/44/60/0/0/1").unwrap().into_raw(),
+    );
+    let c_result = unsafe { CString::from_raw(rt) };
+    let c_result: serde_json::Value = serde_json::from_str(c_result.to_str().unwrap()).unwrap();
+    assert_eq!(c_result["ret_code"], crate::error::NETWORK_ERROR_CODE);
+
+    let failed: serde_json::Value = serde_json::from_str(c_result["result"].as_str().unwrap()).unwrap();
+    assert!(failed.get("signature").is_none());
+    let pool: PresignaturePool = serde_json::from_value(failed["pool"].clone()).unwrap();
+    assert!(pool.is_empty());
+    assert!(pool.is_consumed("presign-0"));
+}
+
+#[cfg(all(test, feature = "local-party-one"))]
+mod end_to_end {
+    use curv::BigInt;
+
+    use crate::ecdsa::server::test_share;
+
+    use super::{verify_signature, PresignaturePool};
+
+    #[test]
+    fn test_presignature_pool() {
+        let share = test_share("m/44/60/0/0/1");
+        let client_shim = &share.client_shim;
+
+        let mut pool = PresignaturePool::new(&share.private_share.id);
+        pool.fill(client_shim, 2).unwrap();
+        assert_eq!(pool.len(), 2);
+
+        // the pool survives a round trip through its persisted form
+        let mut pool: PresignaturePool = serde_json::from_str(&serde_json::to_string(&pool).unwrap()).unwrap();
+
+        let message = BigInt::from(1234);
+        let signature = pool.sign(client_shim, message.clone(), &share.child, &share.path).unwrap();
+        verify_signature(&signature, &share.child.public.q, &message).unwrap();
+        assert_eq!(pool.len(), 1);
+
+        // party one refuses a used presignature as well, here one taken from a
+        // fresh pool that does not know it was spent
+        let presignature = pool.take().unwrap();
+        let presignature_json = serde_json::to_string(&presignature).unwrap();
+        pool.sign_with(client_shim, presignature, message.clone(), &share.child, &share.path).unwrap();
+        let replayed = serde_json::from_str(&presignature_json).unwrap();
+        let mut fresh = PresignaturePool::new(&share.private_share.id);
+        assert!(fresh.sign_with(client_shim, replayed, BigInt::from(4321), &share.child, &share.path).is_err());
+    }
+}
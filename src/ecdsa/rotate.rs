@@ -0,0 +1,210 @@
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+use curv::cryptographic_primitives::twoparty::coin_flip_optimal_rounds;
+use curv::elliptic::curves::secp256_k1::GE;
+use kms::ecdsa::two_party::party1::RotationParty1Message1;
+use kms::rotation::two_party::party2::Rotation2;
+
+use crate::error::ClientError;
+use crate::utilities::{catch_panic, error_to_c_string, success_to_c_string};
+
+use super::super::ClientShim;
+use super::super::Result;
+use super::super::utilities::requests;
+use super::types::PrivateShare;
+use super::ServerReply;
+
+pub(crate) const ROTATE_PATH_PRE: &str = "bitverse/wallet/v1/private/mpc/ecdsa/rotate";
+
+#[derive(Serialize, Deserialize)]
+pub struct RotateFirstReq {
+    pub id: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RotateSecondReq {
+    pub id: String,
+    pub coinFlipParty2FirstMsg: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RotateCommitReq {
+    pub id: String,
+}
+
+/// Refreshes both shares of `private_share` with a coin-flipped factor and
+/// a new Paillier key of party one. The returned share has the same id,
+/// `public.q` and chain code.
+///
+/// Party one keeps the rotated share pending and goes on signing with the
+/// old one until `rotate_commit`, so the new share can be stored safely
+/// first.
+pub fn rotate_begin(client_shim: &ClientShim, private_share: &PrivateShare) -> Result<PrivateShare> {
+    let id = &private_share.id;
+
+    let server_reply: ServerReply = requests::postb(client_shim, &format!("{}/first", ROTATE_PATH_PRE), RotateFirstReq { id: id.clone() })?;
+    let coin_flip_party1_first_message: coin_flip_optimal_rounds::Party1FirstMessage<GE> = server_reply.into_result()?;
+
+    let coin_flip_party2_first_message = Rotation2::key_rotate_first_message(&coin_flip_party1_first_message);
+
+    let rotate_second_req = RotateSecondReq {
+        id: id.clone(),
+        coinFlipParty2FirstMsg: serde_json::to_string(&coin_flip_party2_first_message)?,
+    };
+    let server_reply: ServerReply = requests::postb(client_shim, &format!("{}/second", ROTATE_PATH_PRE), rotate_second_req)?;
+    let (coin_flip_party1_second_message, rotation_party1_first_message): (
+        coin_flip_optimal_rounds::Party1SecondMessage<GE>,
+        RotationParty1Message1,
+    ) = server_reply.into_result()?;
+
+    let random2 = Rotation2::key_rotate_second_message(
+        &coin_flip_party1_second_message,
+        &coin_flip_party2_first_message,
+        &coin_flip_party1_first_message,
+    );
+
+    let master_key = match private_share.master_key.clone().rotate_first_message(&random2, &rotation_party1_first_message) {
+        Ok(mk) => mk,
+        Err(_) => return Err(ClientError::ProofVerification("rotation: party one Paillier key proofs".to_string()).into())
+    };
+
+    if master_key.public.q != private_share.master_key.public.q || master_key.chain_code != private_share.master_key.chain_code {
+        return Err(ClientError::ProofVerification("rotation changed the public key".to_string()).into())
+    }
+
+    Ok(PrivateShare {
+        id: id.clone(),
+        master_key,
+    })
+}
+
+/// Switches party one to the rotated share; the old client share stops
+/// working. Repeating a successful commit is harmless.
+pub fn rotate_commit(client_shim: &ClientShim, id: &str) -> Result<()> {
+    let server_reply: ServerReply = requests::postb(client_shim, &format!("{}/commit", ROTATE_PATH_PRE), RotateCommitReq { id: id.to_string() })?;
+    let _: String = server_reply.into_result()?;
+    Ok(())
+}
+
+/// `rotate_begin` and `rotate_commit` in one go, for callers that do not
+/// need to persist the new share in between.
+pub fn rotate_master_key(client_shim: &ClientShim, private_share: &PrivateShare) -> Result<PrivateShare> {
+    let rotated = rotate_begin(client_shim, private_share)?;
+    rotate_commit(client_shim, &rotated.id)?;
+    Ok(rotated)
+}
+
+/// Runs the rotation of the `PrivateShare` JSON and returns the new share,
+/// still pending on party one. Store it next to the old share, then call
+/// `rotate_private_share_commit` and drop the old one.
+#[no_mangle]
+pub extern "C" fn rotate_private_share_begin(
+    c_endpoint: *const c_char,
+    c_auth_token: *const c_char,
+    c_private_share_json: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_endpoint = unsafe { CStr::from_ptr(c_endpoint) };
+        let endpoint = match raw_endpoint.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw endpoint failed: {}", e)).into())
+        };
+
+        let raw_auth_token = unsafe { CStr::from_ptr(c_auth_token) };
+        let auth_token = match raw_auth_token.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw auth_token failed: {}", e)).into())
+        };
+
+        let raw_private_share_json = unsafe { CStr::from_ptr(c_private_share_json) };
+        let private_share: PrivateShare = match raw_private_share_json.to_str() {
+            Ok(s) => match serde_json::from_str(s) {
+                Ok(p) => p,
+                Err(e) => return error_to_c_string(ClientError::KeyFormat(format!("decoding private_share_json failed: {}", e)).into())
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw private_share_json failed: {}", e)).into())
+        };
+
+        let client_shim = ClientShim::new(endpoint.to_string(), Some(auth_token.to_string()));
+
+        match rotate_begin(&client_shim, &private_share) {
+            Ok(rotated) => success_to_c_string(rotated),
+            Err(e) => {
+                error!("key rotation failed: {}", e);
+                error_to_c_string(e)
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn rotate_private_share_commit(
+    c_endpoint: *const c_char,
+    c_auth_token: *const c_char,
+    c_id: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_endpoint = unsafe { CStr::from_ptr(c_endpoint) };
+        let endpoint = match raw_endpoint.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw endpoint failed: {}", e)).into())
+        };
+
+        let raw_auth_token = unsafe { CStr::from_ptr(c_auth_token) };
+        let auth_token = match raw_auth_token.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw auth_token failed: {}", e)).into())
+        };
+
+        let raw_id = unsafe { CStr::from_ptr(c_id) };
+        let id = match raw_id.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw id failed: {}", e)).into())
+        };
+
+        let client_shim = ClientShim::new(endpoint.to_string(), Some(auth_token.to_string()));
+
+        match rotate_commit(&client_shim, id) {
+            Ok(()) => success_to_c_string(id),
+            Err(e) => {
+                error!("committing key rotation failed: {}", e);
+                error_to_c_string(e)
+            }
+        }
+    })
+}
+
+#[cfg(all(test, feature = "local-party-one"))]
+mod end_to_end {
+    use curv::BigInt;
+
+    use crate::ecdsa::server::test_share;
+    use crate::ecdsa::sign::sign;
+
+    use super::{rotate_begin, rotate_commit};
+
+    #[test]
+    fn test_rotate_master_key() {
+        let share = test_share("m/0/0");
+        let client_shim = &share.client_shim;
+        let private_share = &share.private_share;
+        let (x, y) = (BigInt::from(0), BigInt::from(0));
+
+        let rotated = rotate_begin(client_shim, private_share).unwrap();
+        assert_eq!(rotated.master_key.public.q, private_share.master_key.public.q);
+        assert_eq!(rotated.master_key.chain_code, private_share.master_key.chain_code);
+
+        // still pending: the old share signs, the new one does not
+        let message = BigInt::from(1234);
+        sign(client_shim, message.clone(), &share.child, x.clone(), y.clone(), &private_share.id).unwrap();
+
+        rotate_commit(client_shim, &rotated.id).unwrap();
+        rotate_commit(client_shim, &rotated.id).unwrap();
+
+        let child = rotated.get_child(vec![x.clone(), y.clone()]);
+        sign(client_shim, message.clone(), &child.master_key, x.clone(), y.clone(), &rotated.id).unwrap();
+
+        assert!(sign(client_shim, message, &share.child, x, y, &private_share.id).is_err());
+    }
+}
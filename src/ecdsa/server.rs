@@ -0,0 +1,711 @@
+// In-process party one, for tests only
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use curv::cryptographic_primitives::proofs::sigma_dlog::DLogProof;
+use curv::cryptographic_primitives::twoparty::dh_key_exchange_variant_with_pok_comm::{
+    CommWitness, EcKeyPair, Party1FirstMessage, Party1SecondMessage,
+};
+use curv::cryptographic_primitives::twoparty::coin_flip_optimal_rounds;
+use curv::elliptic::curves::secp256_k1::{FE, GE};
+use kms::chain_code::two_party as chain_code;
+use kms::ecdsa::two_party::{party1, MasterKey1};
+use kms::rotation::two_party::party1::Rotation1;
+use multi_party_ecdsa::protocols::two_party_ecdsa::lindell_2017::{party_one, party_two};
+
+use crate::error::ClientError;
+use crate::utilities::SUCCESS_CODE;
+use crate::Transport;
+
+use super::super::Result;
+use super::batch::{SignBatchFirstRequest, SignBatchSecondRequest};
+use super::presign::{PresignReply, PresignRequest, SignPresignedRequest};
+use super::rotate::{RotateCommitReq, RotateFirstReq, RotateSecondReq, ROTATE_PATH_PRE};
+use super::keygen::{ChainCodeFirstReq, ChainCodeSecondReq, KeyGenAbandonReq, KeyGenSecondReq, KG_PATH_PRE};
+use super::sign::{SignFirstRequest, SignSecondMsgRequest, SignSecondRequest, SIGN_PATH_PRE, SIGN_PATH_VERSION};
+use super::ServerReply;
+
+const SERVER_ERROR_CODE: i32 = 500;
+
+#[derive(Default)]
+struct Session {
+    kg_comm_witness: Option<party_one::CommWitness>,
+    kg_ec_key_pair_party1: Option<party_one::EcKeyPair>,
+    kg_party_one_public_share: Option<GE>,
+    kg_party_two_public_share: Option<GE>,
+    paillier_key_pair: Option<party_one::PaillierKeyPair>,
+    party_one_private: Option<party_one::Party1Private>,
+    cc_comm_witness: Option<CommWitness<GE>>,
+    cc_ec_key_pair1: Option<EcKeyPair<GE>>,
+    master_key: Option<MasterKey1>,
+    eph_key_gen_first_message_party_two: Option<party_two::EphKeyGenFirstMsg>,
+    eph_ec_key_pair_party1: Option<party_one::EphEcKeyPair>,
+    eph_batch: HashMap<usize, (party_two::EphKeyGenFirstMsg, party_one::EphEcKeyPair)>,
+    presignatures: HashMap<String, (party_two::EphKeyGenFirstMsg, party_one::EphEcKeyPair)>,
+    rotate_coin_flip: Option<(coin_flip_optimal_rounds::Party1FirstMessage<GE>, FE, FE)>,
+    rotated_master_key: Option<MasterKey1>,
+    rotation_committed: bool,
+}
+
+/// A party-one implementation speaking the `ServerReply` wire format of the
+/// Bitverse backend, so keygen and signing can run without a network.
+pub struct LocalPartyOne {
+    sessions: Mutex<HashMap<String, Session>>,
+    next_id: Mutex<u64>,
+}
+
+impl LocalPartyOne {
+    pub fn new() -> LocalPartyOne {
+        LocalPartyOne {
+            sessions: Mutex::new(HashMap::new()),
+            next_id: Mutex::new(0),
+        }
+    }
+
+    /// The party-one master key of a finished keygen session.
+    pub fn master_key(&self, id: &str) -> Option<MasterKey1> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions.get(id).and_then(|s| s.master_key.clone())
+    }
+
+    fn route(&self, path: &str, body: &str) -> Result<String> {
+        if path == format!("{}/first", KG_PATH_PRE) {
+            to_result(self.keygen_first())
+        } else if path == format!("{}/second", KG_PATH_PRE) {
+            to_result(self.keygen_second(parse(body)?))
+        } else if path == format!("{}/chaincode/first", KG_PATH_PRE) {
+            to_result(self.chain_code_first(parse(body)?))
+        } else if path == format!("{}/chaincode/second", KG_PATH_PRE) {
+            to_result(self.chain_code_second(parse(body)?))
+        } else if path == format!("{}/abandon", KG_PATH_PRE) {
+            to_result(self.keygen_abandon(parse(body)?))
+        } else if path == format!("{}/first", SIGN_PATH_PRE) {
+            to_result(self.sign_first(parse(body)?))
+        } else if path == format!("{}/second", SIGN_PATH_PRE) {
+            to_result(self.sign_second(parse(body)?))
+        } else if path == format!("{}/batch/first", SIGN_PATH_PRE) {
+            to_result(self.sign_batch_first(parse(body)?))
+        } else if path == format!("{}/batch/second", SIGN_PATH_PRE) {
+            to_result(self.sign_batch_second(parse(body)?))
+        } else if path == format!("{}/presign", SIGN_PATH_PRE) {
+            to_result(self.presign(parse(body)?))
+        } else if path == format!("{}/presigned/second", SIGN_PATH_PRE) {
+            to_result(self.sign_presigned(parse(body)?))
+        } else if path == format!("{}/first", ROTATE_PATH_PRE) {
+            to_result(self.rotate_first(parse(body)?))
+        } else if path == format!("{}/second", ROTATE_PATH_PRE) {
+            to_result(self.rotate_second(parse(body)?))
+        } else if path == format!("{}/commit", ROTATE_PATH_PRE) {
+            to_result(self.rotate_commit(parse(body)?))
+        } else {
+            Err(ClientError::InvalidInput(format!("unknown path {}", path)).into())
+        }
+    }
+
+    fn with_session<T, F>(&self, id: &str, f: F) -> Result<T>
+        where F: FnOnce(&mut Session) -> Result<T>
+    {
+        let mut sessions = self.sessions.lock().unwrap();
+        match sessions.get_mut(id) {
+            Some(session) => f(session),
+            None => Err(ClientError::InvalidInput(format!("unknown session {}", id)).into())
+        }
+    }
+
+    fn keygen_first(&self) -> Result<(String, party_one::KeyGenFirstMsg)> {
+        let (kg_party_one_first_message, kg_comm_witness, kg_ec_key_pair_party1) =
+            MasterKey1::key_gen_first_message();
+
+        let id = {
+            let mut next_id = self.next_id.lock().unwrap();
+            *next_id += 1;
+            format!("local-{}", *next_id)
+        };
+
+        let session = Session {
+            kg_comm_witness: Some(kg_comm_witness),
+            kg_ec_key_pair_party1: Some(kg_ec_key_pair_party1),
+            ..Session::default()
+        };
+        self.sessions.lock().unwrap().insert(id.clone(), session);
+
+        Ok((id, kg_party_one_first_message))
+    }
+
+    fn keygen_second(&self, req: KeyGenSecondReq) -> Result<party1::KeyGenParty1Message2> {
+        let d_log_proof: DLogProof<GE> = parse(&req.d_log_proof)?;
+        self.with_session(&req.id, |session| {
+            let comm_witness = take(&mut session.kg_comm_witness)?;
+            let ec_key_pair_party1 = take(&mut session.kg_ec_key_pair_party1)?;
+            session.kg_party_one_public_share = Some(comm_witness.public_share);
+
+            let (kg_party_one_second_message, paillier_key_pair, party_one_private) =
+                MasterKey1::key_gen_second_message(comm_witness, &ec_key_pair_party1, &d_log_proof);
+
+            session.kg_party_two_public_share = Some(d_log_proof.pk);
+            session.paillier_key_pair = Some(paillier_key_pair);
+            session.party_one_private = Some(party_one_private);
+            Ok(kg_party_one_second_message)
+        })
+    }
+
+    fn chain_code_first(&self, req: ChainCodeFirstReq) -> Result<Party1FirstMessage> {
+        self.with_session(&req.id, |session| {
+            let (cc_party_one_first_message, cc_comm_witness, cc_ec_key_pair1) =
+                chain_code::party1::ChainCode1::chain_code_first_message();
+            session.cc_comm_witness = Some(cc_comm_witness);
+            session.cc_ec_key_pair1 = Some(cc_ec_key_pair1);
+            Ok(cc_party_one_first_message)
+        })
+    }
+
+    fn chain_code_second(&self, req: ChainCodeSecondReq) -> Result<Party1SecondMessage<GE>> {
+        let d_log_proof: DLogProof<GE> = parse(&req.d_log_proof)?;
+        self.with_session(&req.id, |session| {
+            let cc_comm_witness = take(&mut session.cc_comm_witness)?;
+            let cc_ec_key_pair1 = take(&mut session.cc_ec_key_pair1)?;
+
+            let cc_party_one_second_message =
+                chain_code::party1::ChainCode1::chain_code_second_message(cc_comm_witness, &d_log_proof);
+
+            let party1_cc = chain_code::party1::ChainCode1::compute_chain_code(&cc_ec_key_pair1, &d_log_proof.pk);
+
+            let master_key = MasterKey1::set_master_key(
+                &party1_cc.chain_code,
+                take(&mut session.party_one_private)?,
+                &take(&mut session.kg_party_one_public_share)?,
+                &take(&mut session.kg_party_two_public_share)?,
+                take(&mut session.paillier_key_pair)?,
+            );
+            session.master_key = Some(master_key);
+            Ok(cc_party_one_second_message)
+        })
+    }
+
+    fn keygen_abandon(&self, req: KeyGenAbandonReq) -> Result<String> {
+        let mut sessions = self.sessions.lock().unwrap();
+        match sessions.get(&req.id) {
+            Some(session) if session.master_key.is_some() => {
+                Err(ClientError::InvalidInput(format!("keygen already finished for {}", req.id)).into())
+            }
+            Some(_) => {
+                sessions.remove(&req.id);
+                Ok(req.id)
+            }
+            None => Err(ClientError::InvalidInput(format!("unknown session {}", req.id)).into())
+        }
+    }
+
+    fn sign_first(&self, req: SignFirstRequest) -> Result<party_one::EphKeyGenFirstMsg> {
+        let eph_key_gen_first_message_party_two: party_two::EphKeyGenFirstMsg = parse(&req.ephKeyGenFirstMsg)?;
+        self.with_session(&req.id, |session| {
+            if session.master_key.is_none() {
+                return Err(ClientError::InvalidInput(format!("keygen not finished for {}", req.id)).into())
+            }
+            let (sign_party_one_first_message, eph_ec_key_pair_party1) = MasterKey1::sign_first_message();
+            session.eph_key_gen_first_message_party_two = Some(eph_key_gen_first_message_party_two);
+            session.eph_ec_key_pair_party1 = Some(eph_ec_key_pair_party1);
+            Ok(sign_party_one_first_message)
+        })
+    }
+
+    fn sign_second(&self, req: SignSecondRequest) -> Result<party_one::SignatureRecid> {
+        let sign_second_msg_request: SignSecondMsgRequest = parse(&req.signSecondMsgReq)?;
+        self.with_session(&req.id, |session| {
+            let eph_key_gen_first_message_party_two = take(&mut session.eph_key_gen_first_message_party_two)?;
+            let eph_ec_key_pair_party1 = take(&mut session.eph_ec_key_pair_party1)?;
+            let master_key = match &session.master_key {
+                Some(mk) => mk,
+                None => return Err(ClientError::InvalidInput(format!("keygen not finished for {}", req.id)).into())
+            };
+            sign_second_message(master_key, &sign_second_msg_request, &eph_key_gen_first_message_party_two, &eph_ec_key_pair_party1)
+        })
+    }
+
+    /// Ephemeral keys for a whole batch; a new batch drops the unused keys of
+    /// the previous one.
+    fn sign_batch_first(&self, req: SignBatchFirstRequest) -> Result<Vec<ServerReply>> {
+        self.with_session(&req.id, |session| {
+            if session.master_key.is_none() {
+                return Err(ClientError::InvalidInput(format!("keygen not finished for {}", req.id)).into())
+            }
+            session.eph_batch.clear();
+            let mut replies = Vec::with_capacity(req.ephKeyGenFirstMsgs.len());
+            for (index, msg) in req.ephKeyGenFirstMsgs.iter().enumerate() {
+                let reply = parse::<party_two::EphKeyGenFirstMsg>(msg).map(|eph_key_gen_first_message_party_two| {
+                    let (sign_party_one_first_message, eph_ec_key_pair_party1) = MasterKey1::sign_first_message();
+                    session.eph_batch.insert(index, (eph_key_gen_first_message_party_two, eph_ec_key_pair_party1));
+                    sign_party_one_first_message
+                });
+                replies.push(reply_of(reply)?);
+            }
+            Ok(replies)
+        })
+    }
+
+    fn sign_batch_second(&self, req: SignBatchSecondRequest) -> Result<Vec<ServerReply>> {
+        self.with_session(&req.id, |session| {
+            let master_key = match &session.master_key {
+                Some(mk) => mk,
+                None => return Err(ClientError::InvalidInput(format!("keygen not finished for {}", req.id)).into())
+            };
+            let mut replies = Vec::with_capacity(req.items.len());
+            for item in &req.items {
+                // each ephemeral key signs at most once
+                let reply = match session.eph_batch.remove(&item.index) {
+                    Some((eph_key_gen_first_message_party_two, eph_ec_key_pair_party1)) =>
+                        parse::<SignSecondMsgRequest>(&item.signSecondMsgReq).and_then(|sign_second_msg_request| {
+                            sign_second_message(master_key, &sign_second_msg_request, &eph_key_gen_first_message_party_two, &eph_ec_key_pair_party1)
+                        }),
+                    None => Err(ClientError::InvalidInput(format!("no ephemeral key for item {}", item.index)).into()),
+                };
+                replies.push(reply_of(reply)?);
+            }
+            Ok(replies)
+        })
+    }
+
+    fn presign(&self, req: PresignRequest) -> Result<Vec<PresignReply>> {
+        let mut eph_msgs = Vec::with_capacity(req.ephKeyGenFirstMsgs.len());
+        for msg in &req.ephKeyGenFirstMsgs {
+            eph_msgs.push(parse::<party_two::EphKeyGenFirstMsg>(msg)?);
+        }
+        let mut replies = Vec::with_capacity(eph_msgs.len());
+        for eph_key_gen_first_message_party_two in eph_msgs {
+            let presign_id = {
+                let mut next_id = self.next_id.lock().unwrap();
+                *next_id += 1;
+                format!("presign-{}", *next_id)
+            };
+            let (sign_party_one_first_message, eph_ec_key_pair_party1) = MasterKey1::sign_first_message();
+            self.with_session(&req.id, |session| {
+                if session.master_key.is_none() {
+                    return Err(ClientError::InvalidInput(format!("keygen not finished for {}", req.id)).into())
+                }
+                session.presignatures.insert(presign_id.clone(), (eph_key_gen_first_message_party_two, eph_ec_key_pair_party1));
+                Ok(())
+            })?;
+            replies.push(PresignReply {
+                presignId: presign_id,
+                ephKeyGenFirstMsg: serde_json::to_string(&sign_party_one_first_message)?,
+            });
+        }
+        Ok(replies)
+    }
+
+    fn sign_presigned(&self, req: SignPresignedRequest) -> Result<party_one::SignatureRecid> {
+        let sign_second_msg_request: SignSecondMsgRequest = parse(&req.signSecondMsgReq)?;
+        self.with_session(&req.id, |session| {
+            // removed before signing, so a presignature never signs twice
+            let (eph_key_gen_first_message_party_two, eph_ec_key_pair_party1) = match session.presignatures.remove(&req.presignId) {
+                Some(p) => p,
+                None => return Err(ClientError::InvalidInput(format!("unknown or used presignature {}", req.presignId)).into())
+            };
+            let master_key = match &session.master_key {
+                Some(mk) => mk,
+                None => return Err(ClientError::InvalidInput(format!("keygen not finished for {}", req.id)).into())
+            };
+            sign_second_message(master_key, &sign_second_msg_request, &eph_key_gen_first_message_party_two, &eph_ec_key_pair_party1)
+        })
+    }
+
+    fn rotate_first(&self, req: RotateFirstReq) -> Result<coin_flip_optimal_rounds::Party1FirstMessage<GE>> {
+        self.with_session(&req.id, |session| {
+            if session.master_key.is_none() {
+                return Err(ClientError::InvalidInput(format!("keygen not finished for {}", req.id)).into())
+            }
+            let (coin_flip_party1_first_message, seed, blinding) = Rotation1::key_rotate_first_message();
+            session.rotate_coin_flip = Some((coin_flip_party1_first_message.clone(), seed, blinding));
+            Ok(coin_flip_party1_first_message)
+        })
+    }
+
+    fn rotate_second(&self, req: RotateSecondReq) -> Result<(coin_flip_optimal_rounds::Party1SecondMessage<GE>, party1::RotationParty1Message1)> {
+        let coin_flip_party2_first_message: coin_flip_optimal_rounds::Party2FirstMessage<GE> = parse(&req.coinFlipParty2FirstMsg)?;
+        self.with_session(&req.id, |session| {
+            let (coin_flip_party1_first_message, seed, blinding) = take(&mut session.rotate_coin_flip)?;
+            let (coin_flip_party1_second_message, random1) = Rotation1::key_rotate_second_message(
+                &coin_flip_party2_first_message,
+                &coin_flip_party1_first_message,
+                &seed,
+                &blinding,
+            );
+            let master_key = match &session.master_key {
+                Some(mk) => mk,
+                None => return Err(ClientError::InvalidInput(format!("keygen not finished for {}", req.id)).into())
+            };
+            let (rotation_party1_first_message, rotated_master_key) = master_key.rotation_first_message(&random1);
+            // the old key keeps signing until the client commits
+            session.rotated_master_key = Some(rotated_master_key);
+            session.rotation_committed = false;
+            Ok((coin_flip_party1_second_message, rotation_party1_first_message))
+        })
+    }
+
+    fn rotate_commit(&self, req: RotateCommitReq) -> Result<String> {
+        self.with_session(&req.id, |session| {
+            match session.rotated_master_key.take() {
+                Some(mk) => {
+                    session.master_key = Some(mk);
+                    session.rotation_committed = true;
+                    Ok(req.id.clone())
+                }
+                None if session.rotation_committed => Ok(req.id.clone()),
+                None => Err(ClientError::InvalidInput(format!("no rotation pending for {}", req.id)).into())
+            }
+        })
+    }
+}
+
+fn sign_second_message(
+    master_key: &MasterKey1,
+    sign_second_msg_request: &SignSecondMsgRequest,
+    eph_key_gen_first_message_party_two: &party_two::EphKeyGenFirstMsg,
+    eph_ec_key_pair_party1: &party_one::EphEcKeyPair,
+) -> Result<party_one::SignatureRecid> {
+    let path = if sign_second_msg_request.version < SIGN_PATH_VERSION {
+        vec![
+            sign_second_msg_request.x_pos_child_key.clone(),
+            sign_second_msg_request.y_pos_child_key.clone(),
+        ]
+    } else {
+        sign_second_msg_request.path.clone()
+    };
+    let child_master_key = master_key.get_child(path);
+
+    match child_master_key.sign_second_message(
+        &sign_second_msg_request.party_two_sign_message,
+        eph_key_gen_first_message_party_two,
+        eph_ec_key_pair_party1,
+        &sign_second_msg_request.message,
+    ) {
+        Ok(signature) => Ok(signature),
+        Err(_) => Err(ClientError::ProofVerification("party two sign message".to_string()).into())
+    }
+}
+
+impl Transport for LocalPartyOne {
+    fn post(&self, path: &str, body: String) -> Result<String> {
+        self.route(path, &body)
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait::async_trait]
+impl crate::AsyncTransport for LocalPartyOne {
+    async fn post(&self, path: &str, body: String) -> Result<String> {
+        self.route(path, &body)
+    }
+}
+
+fn parse<V>(s: &str) -> Result<V>
+    where V: serde::de::DeserializeOwned
+{
+    match serde_json::from_str(s) {
+        Ok(v) => Ok(v),
+        Err(e) => Err(ClientError::InvalidInput(format!("{}", e)).into())
+    }
+}
+
+fn take<T>(slot: &mut Option<T>) -> Result<T> {
+    match slot.take() {
+        Some(v) => Ok(v),
+        None => Err(ClientError::InvalidInput("protocol step out of order".to_string()).into())
+    }
+}
+
+fn reply_of<T: serde::Serialize>(result: Result<T>) -> Result<ServerReply> {
+    Ok(match result {
+        Ok(v) => ServerReply {
+            retCode: SUCCESS_CODE,
+            retMsg: "OK".to_string(),
+            result: Some(serde_json::to_string(&v)?),
+        },
+        Err(e) => ServerReply {
+            retCode: SERVER_ERROR_CODE,
+            retMsg: format!("{}", e),
+            result: None,
+        },
+    })
+}
+
+fn to_result<T: serde::Serialize>(result: Result<T>) -> Result<String> {
+    Ok(serde_json::to_string(&reply_of(result)?)?)
+}
+
+/// A key generated with a fresh `LocalPartyOne`, the client talking to it
+/// and the child key at `path`, for end-to-end tests.
+#[cfg(test)]
+pub(crate) struct TestShare {
+    pub client_shim: crate::ClientShim,
+    pub private_share: super::PrivateShare,
+    pub path: super::DerivationPath,
+    pub child: kms::ecdsa::two_party::MasterKey2,
+}
+
+#[cfg(test)]
+pub(crate) fn test_share(path: &str) -> TestShare {
+    let client_shim = crate::ClientShim::with_transport(Box::new(LocalPartyOne::new()));
+    let private_share = super::get_master_key(&client_shim).unwrap();
+    let path: super::DerivationPath = path.parse().unwrap();
+    let child = private_share.get_child(path.to_bigints()).master_key;
+    TestShare { client_shim, private_share, path, child }
+}
+
+#[test]
+fn test_keygen_derive_sign_verify() {
+    use curv::BigInt;
+    use multi_party_ecdsa::protocols::two_party_ecdsa::lindell_2017::party_one::{verify, Signature};
+
+    let client_shim = crate::ClientShim::with_transport(Box::new(LocalPartyOne::new()));
+    let private_share = super::get_master_key(&client_shim).unwrap();
+
+    let x = BigInt::from(60);
+    let y = BigInt::from(0);
+    let child = private_share.get_child(vec![x.clone(), y.clone()]);
+
+    let message = BigInt::from(1234);
+    let signature = super::sign::sign(&client_shim, message.clone(), &child.master_key, x, y, &private_share.id).unwrap();
+
+    let sig = Signature { r: signature.r, s: signature.s };
+    assert!(verify(&sig, &child.master_key.public.q, &message).is_ok());
+}
+
+#[test]
+fn test_keygen_resume_from_checkpoint() {
+    use std::sync::Arc;
+    use curv::BigInt;
+    use super::keygen::{advance_keygen, KeygenCheckpoint};
+
+    // fails the next request to a path ending in `fail_on.0`; with `fail_on.1`
+    // the request reaches party one first and only the reply is lost
+    struct Interrupted {
+        party_one: Arc<LocalPartyOne>,
+        fail_on: Mutex<Option<(&'static str, bool)>>,
+    }
+
+    impl crate::Transport for Interrupted {
+        fn post(&self, path: &str, body: String) -> Result<String> {
+            let mut fail_on = self.fail_on.lock().unwrap();
+            match *fail_on {
+                Some((suffix, deliver)) if path.ends_with(suffix) => {
+                    *fail_on = None;
+                    if deliver {
+                        self.party_one.post(path, body)?;
+                    }
+                    Err(ClientError::Network(format!("{} interrupted", path)).into())
+                }
+                _ => self.party_one.post(path, body),
+            }
+        }
+    }
+
+    let party_one = Arc::new(LocalPartyOne::new());
+    let client_shim = |fail_on| crate::ClientShim::with_transport(Box::new(Interrupted {
+        party_one: party_one.clone(),
+        fail_on: Mutex::new(fail_on),
+    }));
+
+    // the app persists every checkpoint and keeps nothing else in memory
+    let client = client_shim(Some(("keygen/second", false)));
+    let checkpoint = advance_keygen(&client, KeygenCheckpoint::Start).unwrap();
+    let stored = serde_json::to_string(&checkpoint).unwrap();
+    drop(checkpoint);
+    assert!(advance_keygen(&client, serde_json::from_str(&stored).unwrap()).is_err());
+
+    // restarted: resume from the stored key_gen_first checkpoint
+    let client = client_shim(Some(("chaincode/first", true)));
+    let checkpoint = advance_keygen(&client, serde_json::from_str(&stored).unwrap()).unwrap();
+    let stored = serde_json::to_string(&checkpoint).unwrap();
+    drop(checkpoint);
+    // the chain code reply is lost after party one handled the request
+    assert!(advance_keygen(&client, serde_json::from_str(&stored).unwrap()).is_err());
+
+    let mut checkpoint: KeygenCheckpoint = serde_json::from_str(&stored).unwrap();
+    while !checkpoint.is_done() {
+        checkpoint = advance_keygen(&client, checkpoint).unwrap();
+        checkpoint = serde_json::from_str(&serde_json::to_string(&checkpoint).unwrap()).unwrap();
+    }
+    let private_share = match checkpoint {
+        KeygenCheckpoint::Done(private_share) => private_share,
+        _ => unreachable!(),
+    };
+    assert_eq!(party_one.master_key(&private_share.id).unwrap().public.q, private_share.master_key.public.q);
+
+    let x = BigInt::from(60);
+    let y = BigInt::from(0);
+    let child = private_share.get_child(vec![x.clone(), y.clone()]);
+    let message = BigInt::from(1234);
+    let signature = super::sign::sign(&client, message.clone(), &child.master_key, x, y, &private_share.id).unwrap();
+    super::sign::verify_signature(&signature, &child.master_key.public.q, &message).unwrap();
+}
+
+/// Party one with its replies to `path` altered by `tamper`, to check that
+/// party two rejects a bad proof and abandons the session on its own.
+#[cfg(test)]
+struct Tampering {
+    party_one: std::sync::Arc<LocalPartyOne>,
+    path: &'static str,
+    tamper: Box<dyn Fn(ServerReply) -> ServerReply + Send + Sync>,
+}
+
+#[cfg(test)]
+impl Tampering {
+    fn reply(&self, path: &str, body: String) -> Result<String> {
+        let reply = self.party_one.route(path, &body)?;
+        if !path.ends_with(self.path) {
+            return Ok(reply)
+        }
+        Ok(serde_json::to_string(&(self.tamper)(serde_json::from_str(&reply)?))?)
+    }
+}
+
+#[cfg(test)]
+impl Transport for Tampering {
+    fn post(&self, path: &str, body: String) -> Result<String> {
+        self.reply(path, body)
+    }
+}
+
+#[cfg(all(test, feature = "async"))]
+#[async_trait::async_trait]
+impl crate::AsyncTransport for Tampering {
+    async fn post(&self, path: &str, body: String) -> Result<String> {
+        self.reply(path, body)
+    }
+}
+
+/// The message of `reply` changed by `tamper`.
+#[cfg(test)]
+fn tampered<T>(tamper: fn(&mut T)) -> Box<dyn Fn(ServerReply) -> ServerReply + Send + Sync>
+    where T: serde::Serialize + serde::de::DeserializeOwned
+{
+    Box::new(move |reply| {
+        let mut msg: T = reply.into_result().unwrap();
+        tamper(&mut msg);
+        reply_of(Ok(msg)).unwrap()
+    })
+}
+
+#[cfg(test)]
+fn bad_proofs() -> Vec<(crate::error::KeygenProof, &'static str, Box<dyn Fn(ServerReply) -> ServerReply + Send + Sync>)> {
+    use curv::elliptic::curves::traits::{ECPoint, ECScalar};
+    use curv::BigInt;
+    use crate::error::KeygenProof;
+
+    vec![
+        (KeygenProof::Commitment, "keygen/second", tampered(|msg: &mut party1::KeyGenParty1Message2| {
+            let comm_witness = &mut msg.ecdh_second_message.comm_witness;
+            comm_witness.public_share = comm_witness.public_share + GE::generator();
+        })),
+        (KeygenProof::DLog, "keygen/second", tampered(|msg: &mut party1::KeyGenParty1Message2| {
+            msg.ecdh_second_message.comm_witness.d_log_proof.challenge_response = FE::new_random();
+        })),
+        (KeygenProof::PaillierCorrectKey, "keygen/second", tampered(|msg: &mut party1::KeyGenParty1Message2| {
+            msg.correct_key_proof.sigma_vec[0] = BigInt::from(2);
+        })),
+        (KeygenProof::PaillierRange, "keygen/second", tampered(|msg: &mut party1::KeyGenParty1Message2| {
+            msg.c_key = BigInt::from(2);
+        })),
+        (KeygenProof::ChainCode, "chaincode/second", tampered(|msg: &mut Party1SecondMessage<GE>| {
+            let comm_witness = &mut msg.comm_witness;
+            comm_witness.public_share = comm_witness.public_share + GE::generator();
+        })),
+    ]
+}
+
+/// Checks that `e` reports `proof` and that party one no longer knows the
+/// session.
+#[cfg(test)]
+fn assert_abandoned(party_one: &LocalPartyOne, e: failure::Error, proof: crate::error::KeygenProof) {
+    use crate::error::KeygenProofError;
+
+    match e.downcast_ref::<ClientError>() {
+        Some(ClientError::KeygenProof(KeygenProofError { id, proof: failed })) => {
+            assert_eq!(*failed, proof);
+            assert!(!party_one.sessions.lock().unwrap().contains_key(id), "{} not abandoned after a bad {}", id, proof);
+        }
+        _ => panic!("unexpected error {} for a bad {}", e, proof),
+    }
+}
+
+#[test]
+fn test_keygen_rejects_bad_proofs() {
+    use std::sync::Arc;
+    use super::keygen::{advance_keygen, KeygenCheckpoint};
+
+    for (proof, path, tamper) in bad_proofs() {
+        let party_one = Arc::new(LocalPartyOne::new());
+        let client_shim = crate::ClientShim::with_transport(Box::new(Tampering { party_one: party_one.clone(), path, tamper }));
+
+        let mut checkpoint = KeygenCheckpoint::Start;
+        let e = loop {
+            checkpoint = match advance_keygen(&client_shim, checkpoint) {
+                Ok(c) => c,
+                Err(e) => break e,
+            };
+            assert!(!checkpoint.is_done(), "keygen finished despite a bad {}", proof);
+        };
+        assert_abandoned(&party_one, e, proof);
+    }
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_async_keygen_rejects_bad_proofs() {
+    use std::sync::Arc;
+    use super::keygen::{advance_keygen_async, KeygenCheckpoint};
+
+    let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    rt.block_on(async {
+        for (proof, path, tamper) in bad_proofs() {
+            let party_one = Arc::new(LocalPartyOne::new());
+            let client_shim = crate::AsyncClientShim::with_transport(Box::new(Tampering { party_one: party_one.clone(), path, tamper }));
+
+            let mut checkpoint = KeygenCheckpoint::Start;
+            let e = loop {
+                checkpoint = match advance_keygen_async(&client_shim, checkpoint).await {
+                    Ok(c) => c,
+                    Err(e) => break e,
+                };
+                assert!(!checkpoint.is_done(), "keygen finished despite a bad {}", proof);
+            };
+            assert_abandoned(&party_one, e, proof);
+        }
+    });
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_async_keygen_sign_verify() {
+    use curv::BigInt;
+    use multi_party_ecdsa::protocols::two_party_ecdsa::lindell_2017::party_one::{verify, Signature};
+
+    let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    rt.block_on(async {
+        let client_shim = crate::AsyncClientShim::with_transport(Box::new(LocalPartyOne::new()));
+        let private_share = super::get_master_key_async(&client_shim).await.unwrap();
+
+        let x = BigInt::from(60);
+        let y = BigInt::from(0);
+        let child = private_share.get_child(vec![x.clone(), y.clone()]);
+
+        let message = BigInt::from(1234);
+        let signature = super::sign::sign_async(&client_shim, message.clone(), &child.master_key, x, y, &private_share.id)
+            .await
+            .unwrap();
+
+        let sig = Signature { r: signature.r, s: signature.s };
+        assert!(verify(&sig, &child.master_key.public.q, &message).is_ok());
+    });
+}
+
+#[test]
+fn test_sign_with_deep_path() {
+    use curv::BigInt;
+
+    let share = test_share("m/44/60/0/0/5");
+    let message = BigInt::from(1234);
+    let signature = super::sign::sign_with_path(&share.client_shim, message, &share.child, &share.path, &share.private_share.id).unwrap();
+    assert!(super::sign::is_low_s(&signature));
+}
@@ -3,31 +3,44 @@ use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
 
 use curv::BigInt;
+use curv::arithmetic::traits::Modulo;
+use curv::elliptic::curves::secp256_k1::{FE, GE};
+use curv::elliptic::curves::traits::{ECPoint, ECScalar};
 use kms::ecdsa::two_party::MasterKey2;
 use kms::ecdsa::two_party::party2;
 use multi_party_ecdsa::protocols::two_party_ecdsa::lindell_2017::party_one;
 use multi_party_ecdsa::protocols::two_party_ecdsa::lindell_2017::party_two;
 
-use crate::utilities::{SUCCESS_CODE, success_to_c_string};
+use crate::error::ClientError;
+use crate::utilities::{catch_panic, success_to_c_string};
 
 use super::super::ClientShim;
 use super::super::Result;
 use super::super::utilities::error_to_c_string;
 use super::super::utilities::requests;
+#[cfg(feature = "async")]
+use super::super::AsyncClientShim;
+#[cfg(feature = "async")]
+use super::super::utilities::async_requests;
 use super::ServerReply;
+use super::path::DerivationPath;
+use super::signature::{self as encoding, SignatureFormat};
+pub use super::signature::{from_compact, from_der, from_recoverable, is_low_s, normalize_s, to_compact, to_der, to_recoverable};
 
-const SIGN_PATH_PRE: &str = "bitverse/wallet/v1/private/mpc/ecdsa/sign";
+pub(crate) const SIGN_PATH_PRE: &str = "bitverse/wallet/v1/private/mpc/ecdsa/sign";
+/// `SignSecondMsgRequest::version` of requests whose `path` is the path to sign at.
+pub const SIGN_PATH_VERSION: u32 = 2;
 
 #[derive(Serialize, Deserialize)]
 pub struct SignFirstRequest{
-    id:String,
-    ephKeyGenFirstMsg:String
+    pub id:String,
+    pub ephKeyGenFirstMsg:String
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct SignSecondRequest{
-    id:String,
-    signSecondMsgReq:String
+    pub id:String,
+    pub signSecondMsgReq:String
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -36,93 +49,251 @@ pub struct SignSecondMsgRequest {
     pub party_two_sign_message: party2::SignMessage,
     pub x_pos_child_key: BigInt,
     pub y_pos_child_key: BigInt,
+    /// Full child path, read by the server from `SIGN_PATH_VERSION` on.
+    #[serde(default)]
+    pub path: Vec<BigInt>,
+    /// Absent (0) in requests that only carry `x_pos_child_key`/`y_pos_child_key`.
+    #[serde(default)]
+    pub version: u32,
 }
 
-pub fn sign(
-    client_shim: &ClientShim,
-    message: BigInt,
-    mk: &MasterKey2,
-    x_pos: BigInt,
-    y_pos: BigInt,
-    id: &String,
-) -> Result<party_one::SignatureRecid> {
+/// Party two's ephemeral key between the first and the second sign round trip.
+#[derive(Serialize, Deserialize)]
+pub struct SignFirstState {
+    eph_comm_witness: party_two::EphCommWitness,
+    eph_ec_key_pair_party2: party_two::EphEcKeyPair,
+}
+
+/// Generates party two's ephemeral key and the `/sign/first` request for it.
+pub fn sign_first_request(id: &str) -> Result<(SignFirstState, SignFirstRequest)> {
     let (eph_key_gen_first_message_party_two, eph_comm_witness, eph_ec_key_pair_party2) =
         MasterKey2::sign_first_message();
 
     let sign_first_req = SignFirstRequest{
         id:id.to_string(),
-        ephKeyGenFirstMsg : serde_json::to_string(&eph_key_gen_first_message_party_two).unwrap()
+        ephKeyGenFirstMsg : serde_json::to_string(&eph_key_gen_first_message_party_two)?
     };
 
-    let server_reply:ServerReply = match requests::postb(client_shim, &format!("{}/first", SIGN_PATH_PRE), &sign_first_req) {
-            Some(s) => s,
-            None => return Err(failure::err_msg("party1 sign first message request failed"))
-        };
-
-    if server_reply.retCode != SUCCESS_CODE || server_reply.result == None {
-        return Err(format_err!("{}:{}",server_reply.retCode,server_reply.retMsg))
-    }
-    let sign_party_one_first_message: party_one::EphKeyGenFirstMsg = match serde_json::from_str(server_reply.result.unwrap().as_str()){
-        Ok(s) => s,
-        Err(e) => return Err(format_err!("{}","Parse content erro from server!"))
+    let state = SignFirstState {
+        eph_comm_witness,
+        eph_ec_key_pair_party2,
     };
+    Ok((state, sign_first_req))
+}
+
+/// Builds the `/sign/second` request from party one's reply to `/sign/first`.
+pub fn sign_second_request(
+    mk: &MasterKey2,
+    state: SignFirstState,
+    server_reply: ServerReply,
+    message: BigInt,
+    path: Vec<BigInt>,
+    id: &str,
+) -> Result<SignSecondRequest> {
+    let sign_party_one_first_message: party_one::EphKeyGenFirstMsg = server_reply.into_result()?;
 
+    sign_second_request_with(mk, state, &sign_party_one_first_message, message, path, id)
+}
 
+/// Same as `sign_second_request`, with party one's first message already decoded.
+pub fn sign_second_request_with(
+    mk: &MasterKey2,
+    state: SignFirstState,
+    sign_party_one_first_message: &party_one::EphKeyGenFirstMsg,
+    message: BigInt,
+    path: Vec<BigInt>,
+    id: &str,
+) -> Result<SignSecondRequest> {
+    check_message(&message)?;
+    // a server that predates `path` signs at x/y, which must not be padded
+    // into a different key; deeper paths need `SIGN_PATH_VERSION`
+    if path.len() < 2 {
+        return Err(ClientError::InvalidInput(format!("signing path needs at least two indices, got {}", path.len())).into())
+    }
     let party_two_sign_message = mk.sign_second_message(
-        &eph_ec_key_pair_party2,
-        eph_comm_witness.clone(),
-        &sign_party_one_first_message,
+        &state.eph_ec_key_pair_party2,
+        state.eph_comm_witness,
+        sign_party_one_first_message,
         &message,
     );
 
-    let signature = match get_signature(
-        client_shim,
+    let x_pos_child_key = path[0].clone();
+    let y_pos_child_key = path[1].clone();
+
+    let sign_secod_msg_request: SignSecondMsgRequest = SignSecondMsgRequest {
         message,
         party_two_sign_message,
-        x_pos,
-        y_pos,
-        &id,
-    ) {
-        Ok(s) => s,
-        Err(e) => return Err(format_err!("ecdsa::get_signature failed failed: {}", e))
+        x_pos_child_key,
+        y_pos_child_key,
+        path,
+        version: SIGN_PATH_VERSION,
     };
 
-    Ok(signature)
+    Ok(SignSecondRequest{
+        id:id.to_string(),
+        signSecondMsgReq:serde_json::to_string(&sign_secod_msg_request)?
+    })
 }
 
-fn get_signature(
+/// Two-party signature of `message` with `mk`, the child key at `x_pos`/`y_pos`.
+/// The result is verified against `mk` and always has a low `s`.
+pub fn sign(
     client_shim: &ClientShim,
     message: BigInt,
-    party_two_sign_message: party2::SignMessage,
-    x_pos_child_key: BigInt,
-    y_pos_child_key: BigInt,
+    mk: &MasterKey2,
+    x_pos: BigInt,
+    y_pos: BigInt,
     id: &String,
 ) -> Result<party_one::SignatureRecid> {
-    let sign_secod_msg_request: SignSecondMsgRequest = SignSecondMsgRequest {
-        message,
-        party_two_sign_message,
-        x_pos_child_key,
-        y_pos_child_key,
-    };
+    sign_at(client_shim, message, mk, vec![x_pos, y_pos], id)
+}
 
-    let sign_second_request = SignSecondRequest{
-        id:id.to_string(),
-        signSecondMsgReq:serde_json::to_string(&sign_secod_msg_request).unwrap()
-    };
+/// Signs with `mk`, the child key already derived at `path`.
+pub fn sign_with_path(
+    client_shim: &ClientShim,
+    message: BigInt,
+    mk: &MasterKey2,
+    path: &DerivationPath,
+    id: &str,
+) -> Result<party_one::SignatureRecid> {
+    sign_at(client_shim, message, mk, path.to_bigints(), id)
+}
 
-    let server_reply:ServerReply = match requests::postb(client_shim, &format!("{}/second", SIGN_PATH_PRE), &sign_second_request) {
-        Some(s) => s,
-        None => return Err(failure::err_msg("party1 sign second message request failed"))
-    };
-    if server_reply.retCode != SUCCESS_CODE || server_reply.result == None {
-        return Err(format_err!("{}:{}",server_reply.retCode,server_reply.retMsg))
+fn sign_at(
+    client_shim: &ClientShim,
+    message: BigInt,
+    mk: &MasterKey2,
+    path: Vec<BigInt>,
+    id: &str,
+) -> Result<party_one::SignatureRecid> {
+    check_message(&message)?;
+    let (state, sign_first_req) = sign_first_request(id)?;
+
+    let server_reply:ServerReply = requests::postb(client_shim, &format!("{}/first", SIGN_PATH_PRE), &sign_first_req)?;
+
+    let sign_second_req = sign_second_request(mk, state, server_reply, message.clone(), path, id)?;
+
+    let signature = get_signature(client_shim, &sign_second_req)?;
+
+    verify_signature(&signature, &mk.public.q, &message)?;
+
+    Ok(normalize_s(signature))
+}
+
+fn get_signature(
+    client_shim: &ClientShim,
+    sign_second_request: &SignSecondRequest,
+) -> Result<party_one::SignatureRecid> {
+    let server_reply:ServerReply = requests::postb(client_shim, &format!("{}/second", SIGN_PATH_PRE), sign_second_request)?;
+
+    let signature: party_one::SignatureRecid = server_reply.into_result()?;
+    Ok(signature)
+}
+
+/// Async flavour of `sign`, sharing the protocol steps with it.
+#[cfg(feature = "async")]
+pub async fn sign_async(
+    client_shim: &AsyncClientShim,
+    message: BigInt,
+    mk: &MasterKey2,
+    x_pos: BigInt,
+    y_pos: BigInt,
+    id: &str,
+) -> Result<party_one::SignatureRecid> {
+    sign_at_async(client_shim, message, mk, vec![x_pos, y_pos], id).await
+}
+
+/// Async flavour of `sign_with_path`.
+#[cfg(feature = "async")]
+pub async fn sign_with_path_async(
+    client_shim: &AsyncClientShim,
+    message: BigInt,
+    mk: &MasterKey2,
+    path: &DerivationPath,
+    id: &str,
+) -> Result<party_one::SignatureRecid> {
+    sign_at_async(client_shim, message, mk, path.to_bigints(), id).await
+}
+
+#[cfg(feature = "async")]
+async fn sign_at_async(
+    client_shim: &AsyncClientShim,
+    message: BigInt,
+    mk: &MasterKey2,
+    path: Vec<BigInt>,
+    id: &str,
+) -> Result<party_one::SignatureRecid> {
+    check_message(&message)?;
+    let (state, sign_first_req) = sign_first_request(id)?;
+
+    let server_reply:ServerReply = async_requests::postb(client_shim, &format!("{}/first", SIGN_PATH_PRE), &sign_first_req).await?;
+
+    let sign_second_req = sign_second_request(mk, state, server_reply, message.clone(), path, id)?;
+
+    let server_reply:ServerReply = async_requests::postb(client_shim, &format!("{}/second", SIGN_PATH_PRE), &sign_second_req).await?;
+
+    let signature: party_one::SignatureRecid = server_reply.into_result()?;
+
+    verify_signature(&signature, &mk.public.q, &message)?;
+
+    Ok(normalize_s(signature))
+}
+
+/// Refuses a message that is 0 mod q before any round trip: its scalar can
+/// not be built and signing it would panic half way through the protocol.
+pub(crate) fn check_message(message: &BigInt) -> Result<()> {
+    if BigInt::modulus(message, &FE::q()) == BigInt::from(0) {
+        return Err(ClientError::InvalidInput("message is zero modulo the curve order".to_string()).into())
     }
+    Ok(())
+}
 
-    let signature: party_one::SignatureRecid = match serde_json::from_str(server_reply.result.unwrap().as_str()){
-        Ok(s) => s,
-        Err(e) => return Err(format_err!("convert to SignatureRecid failed: {}", e))
+/// Checks that `signature` is a valid ECDSA signature of `message` under
+/// `pubkey` and that its `recid` recovers `pubkey`, so a bad or malicious
+/// server reply never reaches a broadcast.
+pub fn verify_signature(signature: &party_one::SignatureRecid, pubkey: &GE, message: &BigInt) -> Result<()> {
+    let q = FE::q();
+    let zero = BigInt::from(0);
+    // already refused before signing, kept for signatures from elsewhere
+    check_message(message)?;
+    if signature.r <= zero || signature.r >= q || signature.s <= zero || signature.s >= q {
+        return Err(ClientError::InvalidSignature("r or s out of range".to_string()).into())
+    }
+
+    let s: FE = ECScalar::from(&signature.s);
+    let s_inv = s.invert();
+    let z: FE = ECScalar::from(message);
+    let r: FE = ECScalar::from(&signature.r);
+    let u1 = z * s_inv;
+    let u2 = r * s_inv;
+
+    let g: GE = ECPoint::generator();
+    let u1_g = g * &u1;
+    let u2_q = *pubkey * &u2;
+    // adding a point to its negation panics in curv instead of giving the
+    // point at infinity
+    if u1_g.x_coor() == u2_q.x_coor() && u1_g.y_coor() != u2_q.y_coor() {
+        return Err(ClientError::InvalidSignature("signature verifies to the point at infinity".to_string()).into())
+    }
+    let point = u1_g + u2_q;
+
+    // the point at infinity has no coordinates
+    let (x, y) = match (point.x_coor(), point.y_coor()) {
+        (Some(x), Some(y)) => (x, y),
+        _ => return Err(ClientError::InvalidSignature("signature verifies to the point at infinity".to_string()).into())
     };
-    Ok(signature)
+    if BigInt::modulus(&x, &q) != signature.r {
+        return Err(ClientError::InvalidSignature("r does not match message and public key".to_string()).into())
+    }
+
+    let y_odd = BigInt::modulus(&y, &BigInt::from(2)) == BigInt::from(1);
+    let x_overflow = x >= q;
+    let recid = (y_odd as u8) | ((x_overflow as u8) << 1);
+    if recid != signature.recid {
+        return Err(ClientError::InvalidSignature(format!("recid {} does not recover the public key, expected {}", signature.recid, recid)).into())
+    }
+
+    Ok(())
 }
 
 #[no_mangle]
@@ -134,57 +305,202 @@ pub extern "C" fn sign_message(
     c_x_pos: i32,//coinType
     c_y_pos: i32,//account:1,2
     c_id: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_endpoint = unsafe { CStr::from_ptr(c_endpoint) };
+        let endpoint = match raw_endpoint.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw endpoint failed: {}", e)).into())
+        };
+
+        let raw_auth_token = unsafe { CStr::from_ptr(c_auth_token) };
+        let auth_token = match raw_auth_token.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw auth_token failed: {}", e)).into())
+        };
+
+        let client_shim = ClientShim::new(endpoint.to_string(), Some(auth_token.to_string()));
+
+        sign_message_with(&client_shim, c_message_le_hex, c_master_key_json, c_x_pos, c_y_pos, c_id)
+    })
+}
+
+/// Same as `sign_message`, using a client created by `new_client_shim`.
+#[no_mangle]
+pub extern "C" fn sign_message_with_client(
+    c_client_shim: *const ClientShim,
+    c_message_le_hex: *const c_char,
+    c_master_key_json: *const c_char,//masterkey
+    c_x_pos: i32,//coinType
+    c_y_pos: i32,//account:1,2
+    c_id: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        let client_shim = match unsafe { c_client_shim.as_ref() } {
+            Some(c) => c,
+            None => return error_to_c_string(ClientError::InvalidInput("client_shim is null".to_string()).into())
+        };
+
+        sign_message_with(client_shim, c_message_le_hex, c_master_key_json, c_x_pos, c_y_pos, c_id)
+    })
+}
+
+/// Same as `sign_message`, with the child key given as a path like `m/44/60/0/0/5`.
+#[no_mangle]
+pub extern "C" fn sign_message_with_path(
+    c_endpoint: *const c_char,
+    c_auth_token: *const c_char,
+    c_message_le_hex: *const c_char,
+    c_master_key_json: *const c_char,//masterkey
+    c_path: *const c_char,//m/44/60/0/0/5
+    c_id: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        sign_message_at_path(c_endpoint, c_auth_token, c_message_le_hex, c_master_key_json, c_path, c_id, SignatureFormat::Json)
+    })
+}
+
+/// Same as `sign_message_with_path`, returning the signature as `c_format`:
+/// `json`, or hex of `der`, `compact` (`r||s`) or `recoverable` (`r||s||recid`).
+#[no_mangle]
+pub extern "C" fn sign_message_with_format(
+    c_endpoint: *const c_char,
+    c_auth_token: *const c_char,
+    c_message_le_hex: *const c_char,
+    c_master_key_json: *const c_char,//masterkey
+    c_path: *const c_char,//m/44/60/0/0/5
+    c_id: *const c_char,
+    c_format: *const c_char,//json, der, compact, recoverable
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_format = unsafe { CStr::from_ptr(c_format) };
+        let format: SignatureFormat = match raw_format.to_str() {
+            Ok(s) => match s.parse() {
+                Ok(f) => f,
+                Err(e) => return error_to_c_string(failure::Error::from(e))
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw format failed: {}", e)).into())
+        };
+
+        sign_message_at_path(c_endpoint, c_auth_token, c_message_le_hex, c_master_key_json, c_path, c_id, format)
+    })
+}
+
+fn sign_message_at_path(
+    c_endpoint: *const c_char,
+    c_auth_token: *const c_char,
+    c_message_le_hex: *const c_char,
+    c_master_key_json: *const c_char,
+    c_path: *const c_char,
+    c_id: *const c_char,
+    format: SignatureFormat,
 ) -> *mut c_char {
     let raw_endpoint = unsafe { CStr::from_ptr(c_endpoint) };
     let endpoint = match raw_endpoint.to_str() {
         Ok(s) => s,
-        Err(e) => return error_to_c_string(format_err!("decoding raw endpoint failed: {}", e))
+        Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw endpoint failed: {}", e)).into())
     };
 
     let raw_auth_token = unsafe { CStr::from_ptr(c_auth_token) };
     let auth_token = match raw_auth_token.to_str() {
         Ok(s) => s,
-        Err(e) => return error_to_c_string(format_err!("decoding raw auth_token failed: {}", e))
+        Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw auth_token failed: {}", e)).into())
     };
 
     let raw_message_hex = unsafe { CStr::from_ptr(c_message_le_hex) };
     let message_hex = match raw_message_hex.to_str() {
         Ok(s) => s,
-        Err(e) => return error_to_c_string(format_err!("decoding raw message_hex failed: {}", e))
+        Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw message_hex failed: {}", e)).into())
     };
 
     let raw_master_key_json = unsafe { CStr::from_ptr(c_master_key_json) };
     let master_key_json = match raw_master_key_json.to_str() {
         Ok(s) => s,
-        Err(e) => return error_to_c_string(format_err!("decoding raw master_key_json failed: {}", e))
+        Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw master_key_json failed: {}", e)).into())
+    };
+
+    let raw_path = unsafe { CStr::from_ptr(c_path) };
+    let path: DerivationPath = match raw_path.to_str() {
+        Ok(s) => match s.parse() {
+            Ok(p) => p,
+            Err(e) => return error_to_c_string(failure::Error::from(e))
+        },
+        Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw path failed: {}", e)).into())
+    };
+
+    let raw_id = unsafe { CStr::from_ptr(c_id) };
+    let id = match raw_id.to_str() {
+        Ok(s) => s,
+        Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw id failed: {}", e)).into())
+    };
+
+    let client_shim = ClientShim::new(endpoint.to_string(), Some(auth_token.to_string()));
+
+    let mk: MasterKey2 = match serde_json::from_str(master_key_json){
+        Ok(s) => s,
+        Err(e) => return error_to_c_string(ClientError::KeyFormat(format!("decoding master_key_json to MasterKey2 failed: {}", e)).into())
+    };
+
+    let mk_child: MasterKey2 = mk.get_child(path.to_bigints());
+
+    let message: BigInt = match serde_json::from_str(message_hex){
+        Ok(s) => s,
+        Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding message_hex failed: {}", e)).into())
+    };
+
+    match sign_with_path(&client_shim, message, &mk_child, &path, id) {
+        Ok(result) => return success_to_c_string(encoding::encode(&result, format)),
+        Err(e) => {
+            error!("signing failed: {}", e);
+            return error_to_c_string(e)
+        }
+    }
+}
+
+fn sign_message_with(
+    client_shim: &ClientShim,
+    c_message_le_hex: *const c_char,
+    c_master_key_json: *const c_char,
+    c_x_pos: i32,
+    c_y_pos: i32,
+    c_id: *const c_char,
+) -> *mut c_char {
+    let raw_message_hex = unsafe { CStr::from_ptr(c_message_le_hex) };
+    let message_hex = match raw_message_hex.to_str() {
+        Ok(s) => s,
+        Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw message_hex failed: {}", e)).into())
+    };
+
+    let raw_master_key_json = unsafe { CStr::from_ptr(c_master_key_json) };
+    let master_key_json = match raw_master_key_json.to_str() {
+        Ok(s) => s,
+        Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw master_key_json failed: {}", e)).into())
     };
 
     let raw_id = unsafe { CStr::from_ptr(c_id) };
     let id = match raw_id.to_str() {
         Ok(s) => s,
-        Err(e) => return error_to_c_string(format_err!("decoding raw id failed: {}", e))
+        Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw id failed: {}", e)).into())
     };
 
     let x: BigInt = BigInt::from(c_x_pos);
 
     let y: BigInt = BigInt::from(c_y_pos);
 
-    let client_shim = ClientShim::new(endpoint.to_string(), Some(auth_token.to_string()));
-
     let mk: MasterKey2 = match serde_json::from_str(master_key_json){
         Ok(s) => s,
-        Err(e) => return error_to_c_string(format_err!("decoding master_key_json to MasterKey2 failed: {}", e))
+        Err(e) => return error_to_c_string(ClientError::KeyFormat(format!("decoding master_key_json to MasterKey2 failed: {}", e)).into())
     };
 
     let mk_child: MasterKey2 = mk.get_child(vec![x.clone(), y.clone()]);
 
     let message: BigInt = match serde_json::from_str(message_hex){
         Ok(s) => s,
-        Err(e) => return error_to_c_string(format_err!("decoding message_hex failed: {}", e))
+        Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding message_hex failed: {}", e)).into())
     };
 
     let sig = sign(
-        &client_shim,
+        client_shim,
         message,
         &mk_child,
         x,
@@ -194,6 +510,75 @@ pub extern "C" fn sign_message(
 
     match sig {
         Ok(result) => return success_to_c_string(result),
-        Err(e) => return error_to_c_string(format_err!("signing to endpoint {} failed: {}", endpoint, e)),
+        Err(e) => {
+            error!("signing failed: {}", e);
+            return error_to_c_string(e)
+        }
+    }
+}
+
+#[test]
+fn test_verify_signature() {
+    let x: FE = ECScalar::new_random();
+    let k: FE = ECScalar::new_random();
+    let g: GE = ECPoint::generator();
+    let pubkey = g * &x;
+    let message = BigInt::from(1234);
+
+    let point = g * &k;
+    let q = FE::q();
+    let r = BigInt::modulus(&point.x_coor().unwrap(), &q);
+    let r_fe: FE = ECScalar::from(&r);
+    let z: FE = ECScalar::from(&message);
+    let s = (k.invert() * (z + r_fe * x)).to_big_int();
+    let recid = (BigInt::modulus(&point.y_coor().unwrap(), &BigInt::from(2)) == BigInt::from(1)) as u8
+        | (((point.x_coor().unwrap() >= q) as u8) << 1);
+
+    let signature = party_one::SignatureRecid { r, s, recid };
+    assert!(verify_signature(&signature, &pubkey, &message).is_ok());
+
+    let wrong_recid = party_one::SignatureRecid { r: signature.r.clone(), s: signature.s.clone(), recid: signature.recid ^ 1 };
+    assert!(verify_signature(&wrong_recid, &pubkey, &message).is_err());
+    assert!(verify_signature(&signature, &pubkey, &BigInt::from(4321)).is_err());
+
+    // a zero message is refused instead of panicking
+    let code = |e: failure::Error| crate::error::error_code(&e);
+    assert_eq!(code(verify_signature(&signature, &pubkey, &BigInt::from(0)).unwrap_err()), crate::error::INVALID_INPUT_CODE);
+    assert_eq!(code(verify_signature(&signature, &pubkey, &q).unwrap_err()), crate::error::INVALID_INPUT_CODE);
+
+    // with the generator as public key, z = q - r gives u1 G + u2 G = 0
+    let z = q.clone() - signature.r.clone();
+    assert_eq!(code(verify_signature(&signature, &g, &z).unwrap_err()), crate::error::INVALID_SIGNATURE_CODE);
+}
+
+#[cfg(all(test, feature = "local-party-one"))]
+mod end_to_end {
+    use curv::BigInt;
+    use curv::elliptic::curves::secp256_k1::FE;
+    use curv::elliptic::curves::traits::ECScalar;
+
+    use crate::ecdsa::server::test_share;
+    use crate::error::{error_code, INVALID_INPUT_CODE};
+
+    use super::{sign_with_path, DerivationPath};
+
+    #[test]
+    fn test_sign_refuses_zero_message() {
+        let share = test_share("m/44/60/0/0/0");
+        for message in vec![BigInt::from(0), FE::q()] {
+            let e = sign_with_path(&share.client_shim, message, &share.child, &share.path, &share.private_share.id).unwrap_err();
+            assert_eq!(error_code(&e), INVALID_INPUT_CODE);
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_sign_refuses_short_path() {
+        let share = test_share("m/44/60/0/0/0");
+        for path in &["m/0", "m/44"] {
+            let path: DerivationPath = path.parse().unwrap();
+            let child = share.private_share.get_child(path.to_bigints()).master_key;
+            let e = sign_with_path(&share.client_shim, BigInt::from(1234), &child, &path, &share.private_share.id).unwrap_err();
+            assert_eq!(error_code(&e), INVALID_INPUT_CODE);
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use std::ffi::CStr;
+use std::fmt;
+use std::os::raw::c_char;
+use std::str::FromStr;
+
+use curv::arithmetic::traits::Converter;
+use curv::elliptic::curves::secp256_k1::FE;
+use curv::elliptic::curves::traits::ECScalar;
+use curv::BigInt;
+use multi_party_ecdsa::protocols::two_party_ecdsa::lindell_2017::party_one;
+
+use crate::error::ClientError;
+use crate::utilities::{catch_panic, error_to_c_string, success_to_c_string, to_fixed_bytes};
+
+/// Encodings of a signature handed to callers.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureFormat {
+    /// `SignatureRecid` with `r` and `s` as JSON BigInts.
+    Json,
+    /// ASN.1 DER, as used by Bitcoin.
+    Der,
+    /// 64 bytes `r || s`.
+    Compact,
+    /// 65 bytes `r || s || recid`, `recid` in 0..=3.
+    Recoverable,
+}
+
+impl FromStr for SignatureFormat {
+    type Err = ClientError;
+
+    fn from_str(s: &str) -> Result<SignatureFormat, ClientError> {
+        match s {
+            "" | "json" => Ok(SignatureFormat::Json),
+            "der" => Ok(SignatureFormat::Der),
+            "compact" => Ok(SignatureFormat::Compact),
+            "recoverable" => Ok(SignatureFormat::Recoverable),
+            _ => Err(ClientError::InvalidInput(format!("unknown signature format {}", s)))
+        }
+    }
+}
+
+impl fmt::Display for SignatureFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SignatureFormat::Json => write!(f, "json"),
+            SignatureFormat::Der => write!(f, "der"),
+            SignatureFormat::Compact => write!(f, "compact"),
+            SignatureFormat::Recoverable => write!(f, "recoverable"),
+        }
+    }
+}
+
+pub fn is_low_s(signature: &party_one::SignatureRecid) -> bool {
+    signature.s <= FE::q() / BigInt::from(2)
+}
+
+/// Replaces a high `s` with `q - s`. The negated signature recovers the
+/// point with the opposite y, so bit 0 of `recid` is flipped as well.
+pub fn normalize_s(signature: party_one::SignatureRecid) -> party_one::SignatureRecid {
+    if is_low_s(&signature) {
+        return signature
+    }
+    party_one::SignatureRecid {
+        r: signature.r,
+        s: FE::q() - signature.s,
+        recid: signature.recid ^ 1,
+    }
+}
+
+pub fn to_compact(signature: &party_one::SignatureRecid) -> Vec<u8> {
+    let mut out = to_fixed_bytes(&signature.r, 32);
+    out.extend(to_fixed_bytes(&signature.s, 32));
+    out
+}
+
+pub fn to_recoverable(signature: &party_one::SignatureRecid) -> Vec<u8> {
+    let mut out = to_compact(signature);
+    out.push(signature.recid);
+    out
+}
+
+pub fn to_der(signature: &party_one::SignatureRecid) -> Vec<u8> {
+    let r = der_integer(&signature.r);
+    let s = der_integer(&signature.s);
+    let mut out = vec![0x30, (r.len() + s.len()) as u8];
+    out.extend(r);
+    out.extend(s);
+    out
+}
+
+/// `r` and `s` from 64 bytes `r || s`; there is no `recid` to recover.
+pub fn from_compact(bytes: &[u8]) -> Result<party_one::Signature, ClientError> {
+    if bytes.len() != 64 {
+        return Err(ClientError::InvalidInput(format!("compact signature has {} bytes, expected 64", bytes.len())))
+    }
+    let signature = party_one::Signature {
+        r: BigInt::from_bytes(&bytes[..32]),
+        s: BigInt::from_bytes(&bytes[32..]),
+    };
+    check_range(&signature.r, &signature.s)?;
+    Ok(signature)
+}
+
+pub fn from_recoverable(bytes: &[u8]) -> Result<party_one::SignatureRecid, ClientError> {
+    if bytes.len() != 65 {
+        return Err(ClientError::InvalidInput(format!("recoverable signature has {} bytes, expected 65", bytes.len())))
+    }
+    if bytes[64] > 3 {
+        return Err(ClientError::InvalidInput(format!("invalid recid {}", bytes[64])))
+    }
+    let signature = from_compact(&bytes[..64])?;
+    Ok(party_one::SignatureRecid { r: signature.r, s: signature.s, recid: bytes[64] })
+}
+
+/// Strict DER: minimal lengths and integers, no trailing bytes.
+pub fn from_der(bytes: &[u8]) -> Result<party_one::Signature, ClientError> {
+    let invalid = |msg: &str| ClientError::InvalidInput(format!("invalid DER signature: {}", msg));
+    if bytes.len() < 8 || bytes[0] != 0x30 {
+        return Err(invalid("not a sequence"))
+    }
+    if bytes[1] as usize != bytes.len() - 2 {
+        return Err(invalid("bad sequence length"))
+    }
+    let (r, rest) = read_der_integer(&bytes[2..]).map_err(|e| invalid(e))?;
+    let (s, rest) = read_der_integer(rest).map_err(|e| invalid(e))?;
+    if !rest.is_empty() {
+        return Err(invalid("trailing bytes"))
+    }
+    check_range(&r, &s)?;
+    Ok(party_one::Signature { r, s })
+}
+
+fn der_integer(n: &BigInt) -> Vec<u8> {
+    let mut bytes = to_fixed_bytes(n, 1);
+    if bytes[0] & 0x80 != 0 {
+        bytes.insert(0, 0);
+    }
+    let mut out = vec![0x02, bytes.len() as u8];
+    out.extend(bytes);
+    out
+}
+
+fn read_der_integer(bytes: &[u8]) -> Result<(BigInt, &[u8]), &'static str> {
+    if bytes.len() < 2 || bytes[0] != 0x02 {
+        return Err("expected an integer")
+    }
+    let len = bytes[1] as usize;
+    if len == 0 || len > 33 || bytes.len() < 2 + len {
+        return Err("bad integer length")
+    }
+    let value = &bytes[2..2 + len];
+    if value[0] & 0x80 != 0 {
+        return Err("negative integer")
+    }
+    if len > 1 && value[0] == 0 && value[1] & 0x80 == 0 {
+        return Err("integer not minimally encoded")
+    }
+    Ok((BigInt::from_bytes(value), &bytes[2 + len..]))
+}
+
+fn check_range(r: &BigInt, s: &BigInt) -> Result<(), ClientError> {
+    let q = FE::q();
+    let zero = BigInt::from(0);
+    if *r <= zero || *r >= q || *s <= zero || *s >= q {
+        return Err(ClientError::InvalidInput("r or s out of range".to_string()))
+    }
+    Ok(())
+}
+
+/// `signature` in `format`: the `SignatureRecid` itself for `Json`, hex for
+/// the binary encodings.
+pub fn encode(signature: &party_one::SignatureRecid, format: SignatureFormat) -> serde_json::Value {
+    match format {
+        SignatureFormat::Json => serde_json::to_value(signature).unwrap(),
+        SignatureFormat::Der => serde_json::Value::String(hex::encode(to_der(signature))),
+        SignatureFormat::Compact => serde_json::Value::String(hex::encode(to_compact(signature))),
+        SignatureFormat::Recoverable => serde_json::Value::String(hex::encode(to_recoverable(signature))),
+    }
+}
+
+/// Re-encodes a `SignatureRecid` JSON as `c_format` (`json`, `der`,
+/// `compact` or `recoverable`), normalising `s` first.
+#[no_mangle]
+pub extern "C" fn encode_signature(
+    c_signature_json: *const c_char,
+    c_format: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_signature_json = unsafe { CStr::from_ptr(c_signature_json) };
+        let signature: party_one::SignatureRecid = match raw_signature_json.to_str() {
+            Ok(s) => match serde_json::from_str(s) {
+                Ok(sig) => sig,
+                Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding signature_json failed: {}", e)).into())
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw signature_json failed: {}", e)).into())
+        };
+
+        let raw_format = unsafe { CStr::from_ptr(c_format) };
+        let format: SignatureFormat = match raw_format.to_str() {
+            Ok(s) => match s.parse() {
+                Ok(f) => f,
+                Err(e) => return error_to_c_string(failure::Error::from(e))
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw format failed: {}", e)).into())
+        };
+
+        if let Err(e) = check_range(&signature.r, &signature.s) {
+            return error_to_c_string(e.into())
+        }
+
+        success_to_c_string(encode(&normalize_s(signature), format))
+    })
+}
+
+/// Decodes a `der`, `compact` or `recoverable` hex signature into a
+/// `SignatureRecid` JSON; `recid` is 0 unless the encoding carries it.
+#[no_mangle]
+pub extern "C" fn decode_signature(
+    c_signature_hex: *const c_char,
+    c_format: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_signature_hex = unsafe { CStr::from_ptr(c_signature_hex) };
+        let bytes = match raw_signature_hex.to_str() {
+            Ok(s) => match hex::decode(s.trim_start_matches("0x")) {
+                Ok(b) => b,
+                Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding signature hex failed: {}", e)).into())
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw signature_hex failed: {}", e)).into())
+        };
+
+        let raw_format = unsafe { CStr::from_ptr(c_format) };
+        let format: SignatureFormat = match raw_format.to_str() {
+            Ok(s) => match s.parse() {
+                Ok(f) => f,
+                Err(e) => return error_to_c_string(failure::Error::from(e))
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw format failed: {}", e)).into())
+        };
+
+        let signature = match format {
+            SignatureFormat::Der => from_der(&bytes).map(|s| party_one::SignatureRecid { r: s.r, s: s.s, recid: 0 }),
+            SignatureFormat::Compact => from_compact(&bytes).map(|s| party_one::SignatureRecid { r: s.r, s: s.s, recid: 0 }),
+            SignatureFormat::Recoverable => from_recoverable(&bytes),
+            SignatureFormat::Json => Err(ClientError::InvalidInput("json is not a binary signature format".to_string())),
+        };
+
+        match signature {
+            Ok(signature) => success_to_c_string(signature),
+            Err(e) => error_to_c_string(e.into())
+        }
+    })
+}
+
+#[test]
+fn test_signature_encodings() {
+    let q = FE::q();
+    let high = party_one::SignatureRecid { r: BigInt::from(1), s: q.clone() - BigInt::from(2), recid: 1 };
+    assert!(!is_low_s(&high));
+    let low = normalize_s(high);
+    assert!(is_low_s(&low));
+    assert_eq!(low.s, BigInt::from(2));
+    assert_eq!(low.recid, 0);
+
+    assert_eq!(hex::encode(to_der(&low)), "3006020101020102");
+    let r_high = party_one::SignatureRecid { r: BigInt::from(0x80), s: BigInt::from(0x7f), recid: 0 };
+    assert_eq!(hex::encode(to_der(&r_high)), "30070202008002017f");
+
+    let sig = party_one::SignatureRecid {
+        r: BigInt::from_hex("28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276"),
+        s: BigInt::from_hex("67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"),
+        recid: 1,
+    };
+    let der = from_der(&to_der(&sig)).unwrap();
+    assert_eq!((der.r, der.s), (sig.r.clone(), sig.s.clone()));
+    let compact = from_compact(&to_compact(&sig)).unwrap();
+    assert_eq!((compact.r, compact.s), (sig.r.clone(), sig.s.clone()));
+    let recoverable = from_recoverable(&to_recoverable(&sig)).unwrap();
+    assert_eq!(recoverable.recid, 1);
+
+    assert!(from_der(&hex::decode("300702020001020101").unwrap()).is_err());
+    assert!(from_der(&hex::decode("3006020101020102ff").unwrap()).is_err());
+    assert!(from_compact(&[0u8; 64]).is_err());
+    let mut bad_recid = to_recoverable(&sig);
+    bad_recid[64] = 4;
+    assert!(from_recoverable(&bad_recid).is_err());
+}
@@ -1,7 +1,8 @@
 use std::ffi::CString;
 use std::os::raw::c_char;
 
+/// Smoke test for the bindings; the string must be released with `free_char`.
 #[no_mangle]
-pub fn hello() -> *mut c_char {
+pub extern "C" fn hello() -> *mut c_char {
     CString::new("Hello BW MPC!").unwrap().into_raw()
-}
\ No newline at end of file
+}
@@ -0,0 +1,326 @@
+use std::ffi::CStr;
+use std::fmt;
+use std::os::raw::c_char;
+use std::str::FromStr;
+
+use bitcoin::hashes::{hash160, Hash};
+use bitcoin::util::base58;
+use curv::arithmetic::traits::Converter;
+use curv::elliptic::curves::secp256_k1::GE;
+use curv::elliptic::curves::traits::ECPoint;
+use curv::BigInt;
+use kms::ecdsa::two_party::{hd_key, MasterKey2};
+
+use crate::error::ClientError;
+use crate::utilities::{catch_panic, error_to_c_string, success_to_c_string, to_fixed_bytes};
+
+use super::path::DerivationPath;
+
+/// `mpub` on mainnet, the default: these keys do not derive like BIP32, so
+/// they must not look like an `xpub` to other wallets.
+pub const MPUB_VERSION: [u8; 4] = [0x03, 0xa3, 0xfd, 0xc2];
+/// `mtub` on testnet.
+pub const MTUB_VERSION: [u8; 4] = [0x03, 0xa5, 0x6c, 0xd5];
+/// The BIP32 `xpub` version, refused unless set explicitly.
+pub const XPUB_VERSION: [u8; 4] = [0x04, 0x88, 0xb2, 0x1e];
+/// The BIP32 `tpub` version, refused unless set explicitly.
+pub const TPUB_VERSION: [u8; 4] = [0x04, 0x35, 0x87, 0xcf];
+
+const PARITY_BIT: u8 = 0x80;
+
+/// The public half of a `MasterKey2` at some path, enough to derive child
+/// public keys without a share.
+///
+/// It is serialised in the BIP32 layout, but the two-party chain code is a
+/// curve point rather than 32 random bytes: its x coordinate takes the chain
+/// code field and the parity of its y is kept in the top bit of the depth
+/// byte. Derivation follows `MasterKey2::get_child`, not BIP32, so only this
+/// library derives the right children from it; a BIP32 wallet given one with
+/// the `xpub` version would derive other addresses.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtendedPublicKey {
+    pub version: [u8; 4],
+    pub depth: u8,
+    pub parent_fingerprint: [u8; 4],
+    pub child_number: u32,
+    /// Compressed chain code point, as in `MasterKey2::chain_code`.
+    pub chain_code: BigInt,
+    pub public_key: GE,
+}
+
+/// The child public key of `q` and `chain_code` at `path`, exactly as
+/// `MasterKey2::get_child(path).public.q`.
+pub fn derive_public_key(q: &GE, chain_code: &BigInt, path: &DerivationPath) -> GE {
+    let (public_key, _, _) = hd_key(path.to_bigints(), q, chain_code);
+    public_key
+}
+
+impl ExtendedPublicKey {
+    /// The extended public key of `mk` itself, or of its child at `path`.
+    ///
+    /// The BIP32 `XPUB_VERSION` and `TPUB_VERSION` are refused: the chain
+    /// code of a two-party key is a curve point and children are derived as
+    /// in `MasterKey2::get_child`, so a BIP32 wallet importing such a key
+    /// would derive other addresses than ours and miss the funds. A caller
+    /// that really wants them sets `version` on the result.
+    pub fn from_master_key(mk: &MasterKey2, path: Option<&DerivationPath>, version: [u8; 4]) -> Result<ExtendedPublicKey, ClientError> {
+        if is_bip32_version(&version) {
+            return Err(ClientError::InvalidInput(format!("version {} is BIP32's, but these keys do not derive like BIP32", hex::encode(version))))
+        }
+        let root = ExtendedPublicKey {
+            version,
+            depth: 0,
+            parent_fingerprint: [0u8; 4],
+            child_number: 0,
+            chain_code: mk.chain_code.clone(),
+            public_key: mk.public.q,
+        };
+        match path {
+            Some(path) => root.derive(path),
+            None => Ok(root),
+        }
+    }
+
+    /// Public derivation at `path`, matching `MasterKey2::get_child(path)`
+    /// on the key this was exported from.
+    pub fn derive(&self, path: &DerivationPath) -> Result<ExtendedPublicKey, ClientError> {
+        let indices = path.indices();
+        let depth = self.depth as usize + indices.len();
+        if depth >= PARITY_BIT as usize {
+            return Err(ClientError::InvalidInput(format!("depth {} is too deep for an extended key", depth)))
+        }
+
+        let (public_key, _, chain_code) = hd_key(path.to_bigints(), &self.public_key, &self.chain_code);
+
+        // the prefix of a single `hd_key` call takes the same steps, so this
+        // is the parent of `public_key`
+        let parent = match DerivationPath::new(indices[..indices.len() - 1].to_vec()) {
+            Ok(parent_path) => derive_public_key(&self.public_key, &self.chain_code, &parent_path),
+            Err(_) => self.public_key,
+        };
+
+        Ok(ExtendedPublicKey {
+            version: self.version,
+            depth: depth as u8,
+            parent_fingerprint: fingerprint(&parent),
+            child_number: indices[indices.len() - 1],
+            chain_code: chain_code.bytes_compressed_to_big_int(),
+            public_key,
+        })
+    }
+
+    pub fn fingerprint(&self) -> [u8; 4] {
+        fingerprint(&self.public_key)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let chain_code = to_fixed_bytes(&self.chain_code, 33);
+        let odd = chain_code[0] == 0x03;
+
+        let mut out = Vec::with_capacity(78);
+        out.extend_from_slice(&self.version);
+        out.push(self.depth | if odd { PARITY_BIT } else { 0 });
+        out.extend_from_slice(&self.parent_fingerprint);
+        out.extend_from_slice(&self.child_number.to_be_bytes());
+        out.extend_from_slice(&chain_code[1..]);
+        out.extend(compressed(&self.public_key));
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<ExtendedPublicKey, ClientError> {
+        if bytes.len() != 78 {
+            return Err(ClientError::InvalidInput(format!("extended key has {} bytes, expected 78", bytes.len())))
+        }
+        let mut version = [0u8; 4];
+        version.copy_from_slice(&bytes[0..4]);
+        let depth = bytes[4] & !PARITY_BIT;
+        let mut parent_fingerprint = [0u8; 4];
+        parent_fingerprint.copy_from_slice(&bytes[5..9]);
+        let mut child_number = [0u8; 4];
+        child_number.copy_from_slice(&bytes[9..13]);
+
+        let mut chain_code = vec![if bytes[4] & PARITY_BIT != 0 { 0x03 } else { 0x02 }];
+        chain_code.extend_from_slice(&bytes[13..45]);
+        if GE::from_bytes(&chain_code).is_err() {
+            return Err(ClientError::KeyFormat("chain code is not a curve point".to_string()))
+        }
+
+        let public_key = match GE::from_bytes(&bytes[45..78]) {
+            Ok(pk) => pk,
+            Err(_) => return Err(ClientError::KeyFormat("invalid public key".to_string()))
+        };
+
+        Ok(ExtendedPublicKey {
+            version,
+            depth,
+            parent_fingerprint,
+            child_number: u32::from_be_bytes(child_number),
+            chain_code: BigInt::from_bytes(&chain_code),
+            public_key,
+        })
+    }
+}
+
+impl fmt::Display for ExtendedPublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", base58::check_encode_slice(&self.to_bytes()))
+    }
+}
+
+impl FromStr for ExtendedPublicKey {
+    type Err = ClientError;
+
+    fn from_str(s: &str) -> Result<ExtendedPublicKey, ClientError> {
+        match base58::from_check(s) {
+            Ok(bytes) => ExtendedPublicKey::from_bytes(&bytes),
+            Err(e) => Err(ClientError::InvalidInput(format!("invalid extended key: {}", e)))
+        }
+    }
+}
+
+fn compressed(q: &GE) -> Vec<u8> {
+    to_fixed_bytes(&q.bytes_compressed_to_big_int(), 33)
+}
+
+fn fingerprint(q: &GE) -> [u8; 4] {
+    let hash = hash160::Hash::hash(&compressed(q));
+    let mut out = [0u8; 4];
+    out.copy_from_slice(&hash[..4]);
+    out
+}
+
+pub fn is_bip32_version(version: &[u8; 4]) -> bool {
+    *version == XPUB_VERSION || *version == TPUB_VERSION
+}
+
+fn parse_version(s: &str) -> Result<[u8; 4], ClientError> {
+    let bytes = match hex::decode(s.trim_start_matches("0x")) {
+        Ok(b) if b.len() == 4 => b,
+        _ => return Err(ClientError::InvalidInput(format!("version must be 4 bytes of hex, got {}", s)))
+    };
+    let mut version = [0u8; 4];
+    version.copy_from_slice(&bytes);
+    Ok(version)
+}
+
+/// The extended public key at `c_path` (empty for the master key itself)
+/// with `c_version` as 8 hex digits (empty for `03a3fdc2`, `mpub`).
+///
+/// The key is not BIP32-compatible: its children follow
+/// `MasterKey2::get_child`, so only `xpub_derive_public_key` derives them.
+/// The BIP32 versions `0488b21e` and `043587cf` are refused so that standard
+/// wallets do not accept it and derive different addresses.
+#[no_mangle]
+pub extern "C" fn export_xpub(
+    c_master_key_json: *const c_char,//masterkey
+    c_path: *const c_char,//m/44/0/0
+    c_version: *const c_char,//03a3fdc2
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_master_key_json = unsafe { CStr::from_ptr(c_master_key_json) };
+        let master_key_json = match raw_master_key_json.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw master_key_json failed: {}", e)).into())
+        };
+
+        let raw_path = unsafe { CStr::from_ptr(c_path) };
+        let path: Option<DerivationPath> = match raw_path.to_str() {
+            Ok("") | Ok("m") => None,
+            Ok(s) => match s.parse() {
+                Ok(p) => Some(p),
+                Err(e) => return error_to_c_string(failure::Error::from(e))
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw path failed: {}", e)).into())
+        };
+
+        let raw_version = unsafe { CStr::from_ptr(c_version) };
+        let version = match raw_version.to_str() {
+            Ok("") => MPUB_VERSION,
+            Ok(s) => match parse_version(s) {
+                Ok(v) => v,
+                Err(e) => return error_to_c_string(e.into())
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw version failed: {}", e)).into())
+        };
+
+        let mk: MasterKey2 = match serde_json::from_str(master_key_json){
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::KeyFormat(format!("decoding master_key_json to MasterKey2 failed: {}", e)).into())
+        };
+
+        match ExtendedPublicKey::from_master_key(&mk, path.as_ref(), version) {
+            Ok(xpub) => success_to_c_string(xpub.to_string()),
+            Err(e) => error_to_c_string(e.into())
+        }
+    })
+}
+
+/// The public key at `c_path` below the extended key `c_xpub`, in the same
+/// form as `get_public_share_key_with_derive`.
+#[no_mangle]
+pub extern "C" fn xpub_derive_public_key(
+    c_xpub: *const c_char,
+    c_path: *const c_char,//0/5
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_xpub = unsafe { CStr::from_ptr(c_xpub) };
+        let xpub: ExtendedPublicKey = match raw_xpub.to_str() {
+            Ok(s) => match s.parse() {
+                Ok(x) => x,
+                Err(e) => return error_to_c_string(failure::Error::from(e))
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw xpub failed: {}", e)).into())
+        };
+
+        let raw_path = unsafe { CStr::from_ptr(c_path) };
+        let path: DerivationPath = match raw_path.to_str() {
+            Ok(s) => match s.parse() {
+                Ok(p) => p,
+                Err(e) => return error_to_c_string(failure::Error::from(e))
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw path failed: {}", e)).into())
+        };
+
+        let pk = derive_public_key(&xpub.public_key, &xpub.chain_code, &path);
+        success_to_c_string(pk.get_element())
+    })
+}
+
+#[test]
+fn test_xpub() {
+    let fs = std::fs::read_to_string("testAssets/mk.json").unwrap();
+    let private_share: super::PrivateShare = serde_json::from_str(&fs).unwrap();
+    let mk = &private_share.master_key;
+
+    let root = ExtendedPublicKey::from_master_key(mk, None, MPUB_VERSION).unwrap();
+    let encoded = root.to_string();
+    assert!(encoded.starts_with("mpub"));
+    assert_eq!(encoded.parse::<ExtendedPublicKey>().unwrap(), root);
+
+    // the same public key as get_public_share_key_with_derive(mk, 60, 1)
+    let path: DerivationPath = "m/60/1".parse().unwrap();
+    let child = mk.get_child(path.to_bigints());
+    assert_eq!(derive_public_key(&root.public_key, &root.chain_code, &path), child.public.q);
+
+    let account: DerivationPath = "m/44/60".parse().unwrap();
+    let xpub = ExtendedPublicKey::from_master_key(mk, Some(&account), MTUB_VERSION).unwrap();
+    assert_eq!(xpub.depth, 2);
+    assert_eq!(xpub.child_number, 60);
+    assert_eq!(xpub.parent_fingerprint, fingerprint(&mk.get_child(vec![BigInt::from(44)]).public.q));
+    let decoded: ExtendedPublicKey = xpub.to_string().parse().unwrap();
+    assert!(xpub.to_string().starts_with("mtub"));
+    assert_eq!(decoded, xpub);
+
+    // BIP32 versions only when set on purpose
+    assert!(ExtendedPublicKey::from_master_key(mk, None, XPUB_VERSION).is_err());
+    assert!(ExtendedPublicKey::from_master_key(mk, None, TPUB_VERSION).is_err());
+    let bip32 = ExtendedPublicKey { version: XPUB_VERSION, ..root.clone() };
+    assert!(bip32.to_string().starts_with("xpub"));
+
+    // watch-only derivation below the exported key
+    let address: DerivationPath = "m/0/5".parse().unwrap();
+    assert_eq!(
+        derive_public_key(&decoded.public_key, &decoded.chain_code, &address),
+        mk.get_child(account.to_bigints()).get_child(address.to_bigints()).public.q
+    );
+}
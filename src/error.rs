@@ -0,0 +1,158 @@
+use std::fmt;
+
+use crate::utilities::SYSTEM_ERROR_CODE;
+
+/// The request never produced a usable reply (DNS, TLS, connection reset, timeout).
+pub const NETWORK_ERROR_CODE: i32 = 10104001;
+/// The server answered with a `ServerReply` whose `retCode` is not success.
+pub const SERVER_REJECTED_CODE: i32 = 10104002;
+/// The server answered, but the payload could not be decoded.
+pub const MALFORMED_RESPONSE_CODE: i32 = 10104003;
+/// A zero-knowledge proof or commitment sent by the server did not verify.
+pub const PROOF_VERIFICATION_CODE: i32 = 10104004;
+/// An argument passed across the FFI boundary was null, not UTF-8 or not valid JSON.
+pub const INVALID_INPUT_CODE: i32 = 10104005;
+/// Key material (`MasterKey2`, `PrivateShare`, public keys) could not be decoded.
+pub const KEY_FORMAT_CODE: i32 = 10104006;
+/// The server answered with a non-success HTTP status (load balancer, gateway, ...).
+pub const HTTP_STATUS_CODE: i32 = 10104007;
+/// The signature returned by the server does not verify against the client's public key.
+pub const INVALID_SIGNATURE_CODE: i32 = 10104008;
+/// A sealed `PrivateShare` could not be opened: wrong password or tampered envelope.
+pub const DECRYPTION_CODE: i32 = 10104009;
+
+/// Every failure the client can report, each with a stable `ret_code`.
+///
+/// The codes are part of the FFI contract: mobile apps switch on them to pick
+/// a message and to decide whether the call may be retried, so existing values
+/// must never be renumbered.
+#[derive(Debug, Fail)]
+pub enum ClientError {
+    #[fail(display = "network error: {}", _0)]
+    Network(String),
+    #[fail(display = "server rejected request: {}:{}", code, msg)]
+    ServerRejected { code: i32, msg: String },
+    #[fail(display = "malformed server response: {}", _0)]
+    MalformedResponse(String),
+    #[fail(display = "proof verification failed: {}", _0)]
+    ProofVerification(String),
+    #[fail(display = "proof verification failed: {}", _0)]
+    KeygenProof(KeygenProofError),
+    #[fail(display = "invalid input: {}", _0)]
+    InvalidInput(String),
+    #[fail(display = "invalid key format: {}", _0)]
+    KeyFormat(String),
+    #[fail(display = "http status {}: {}", status, body)]
+    Http { status: u16, body: String },
+    #[fail(display = "invalid signature from server: {}", _0)]
+    InvalidSignature(String),
+    #[fail(display = "decryption failed: {}", _0)]
+    Decryption(String),
+    #[fail(display = "system error: {}", _0)]
+    System(String),
+}
+
+impl ClientError {
+    pub fn code(&self) -> i32 {
+        match self {
+            ClientError::Network(_) => NETWORK_ERROR_CODE,
+            ClientError::ServerRejected { .. } => SERVER_REJECTED_CODE,
+            ClientError::MalformedResponse(_) => MALFORMED_RESPONSE_CODE,
+            ClientError::ProofVerification(_) => PROOF_VERIFICATION_CODE,
+            ClientError::KeygenProof(_) => PROOF_VERIFICATION_CODE,
+            ClientError::InvalidInput(_) => INVALID_INPUT_CODE,
+            ClientError::KeyFormat(_) => KEY_FORMAT_CODE,
+            ClientError::Http { .. } => HTTP_STATUS_CODE,
+            ClientError::InvalidSignature(_) => INVALID_SIGNATURE_CODE,
+            ClientError::Decryption(_) => DECRYPTION_CODE,
+            ClientError::System(_) => SYSTEM_ERROR_CODE,
+        }
+    }
+
+    /// Whether repeating the same call can reasonably succeed.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClientError::Network(_) => true,
+            ClientError::Http { status, .. } => *status >= 500 || *status == 429,
+            _ => false,
+        }
+    }
+}
+
+/// The keygen proof or commitment sent by party one that did not verify.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeygenProof {
+    /// Party one's public share or DLog proof does not open its commitment.
+    Commitment,
+    /// The DLog proof of party one's public share.
+    DLog,
+    /// The proof that the Paillier ciphertext of party one's share is in range.
+    PaillierRange,
+    /// The proof that party one's Paillier key is well formed.
+    PaillierCorrectKey,
+    /// The commitment or DLog proof of party one's chain code share.
+    ChainCode,
+}
+
+impl fmt::Display for KeygenProof {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            KeygenProof::Commitment => "commitment",
+            KeygenProof::DLog => "dlog",
+            KeygenProof::PaillierRange => "paillier range",
+            KeygenProof::PaillierCorrectKey => "paillier correct key",
+            KeygenProof::ChainCode => "chain code",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A keygen aborted because party one sent a proof that does not verify.
+///
+/// The session `id` is the one party one created in the first round; the
+/// client has already asked it to abandon the session, and the partial key
+/// must not be resumed.
+#[derive(Debug, Fail)]
+#[fail(display = "keygen {} {} proof did not verify", id, proof)]
+pub struct KeygenProofError {
+    pub id: String,
+    pub proof: KeygenProof,
+}
+
+/// Truncates a reply body so it can be carried in an error message.
+pub fn body_snippet(body: &str) -> String {
+    const MAX_SNIPPET_CHARS: usize = 256;
+    if body.chars().count() <= MAX_SNIPPET_CHARS {
+        return body.to_string()
+    }
+    let snippet: String = body.chars().take(MAX_SNIPPET_CHARS).collect();
+    format!("{}...", snippet)
+}
+
+/// Extracts the `ret_code` of an error, falling back to `SYSTEM_ERROR_CODE`
+/// for errors that did not originate from a `ClientError`.
+pub fn error_code(e: &failure::Error) -> i32 {
+    match e.downcast_ref::<ClientError>() {
+        Some(client_error) => client_error.code(),
+        None => SYSTEM_ERROR_CODE,
+    }
+}
+
+/// The HTTP status of a `ClientError::Http`, for `CResult.http_status`.
+pub fn http_status(e: &failure::Error) -> Option<u16> {
+    match e.downcast_ref::<ClientError>() {
+        Some(ClientError::Http { status, .. }) => Some(*status),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_error_code() {
+    let e: failure::Error = ClientError::Network("timeout".to_string()).into();
+    assert_eq!(error_code(&e), NETWORK_ERROR_CODE);
+    let e: failure::Error = ClientError::KeygenProof(KeygenProofError { id: "abc".to_string(), proof: KeygenProof::DLog }).into();
+    assert_eq!(error_code(&e), PROOF_VERIFICATION_CODE);
+    assert_eq!(format!("{}", e), "proof verification failed: keygen abc dlog proof did not verify");
+    let e = format_err!("unexpected");
+    assert_eq!(error_code(&e), SYSTEM_ERROR_CODE);
+}
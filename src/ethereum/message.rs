@@ -0,0 +1,121 @@
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+use kms::ecdsa::two_party::MasterKey2;
+
+use crate::ecdsa::DerivationPath;
+use crate::error::ClientError;
+use crate::utilities::{catch_panic, error_to_c_string, success_to_c_string};
+use crate::ClientShim;
+
+use super::super::Result;
+use super::{from_hex_prefixed, keccak256, sign_digest};
+
+/// The EIP-191 version `0x45` digest used by `personal_sign`:
+/// `keccak256("\x19Ethereum Signed Message:\n" || len(message) || message)`.
+pub fn personal_message_hash(message: &[u8]) -> [u8; 32] {
+    let mut data = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    data.extend_from_slice(message);
+    keccak256(&data)
+}
+
+/// `personal_sign` with `mk`, the child key already derived at `path`.
+pub fn personal_sign(
+    client_shim: &ClientShim,
+    message: &[u8],
+    mk: &MasterKey2,
+    path: &DerivationPath,
+    id: &str,
+) -> Result<String> {
+    sign_digest(client_shim, &personal_message_hash(message), mk, path, id)
+}
+
+/// Dapps pass either `0x` hex or plain text to `personal_sign`.
+fn message_bytes(message: &str) -> Vec<u8> {
+    if message.starts_with("0x") {
+        if let Ok(bytes) = from_hex_prefixed(message) {
+            return bytes
+        }
+    }
+    message.as_bytes().to_vec()
+}
+
+/// `personal_sign` of `c_message` (`0x` hex bytes or UTF-8 text) with the
+/// child key at `c_path`; the result is the 65 byte signature as hex.
+#[no_mangle]
+pub extern "C" fn eth_personal_sign(
+    c_endpoint: *const c_char,
+    c_auth_token: *const c_char,
+    c_message: *const c_char,
+    c_master_key_json: *const c_char,//masterkey
+    c_path: *const c_char,//m/44/60/0/0/0
+    c_id: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_endpoint = unsafe { CStr::from_ptr(c_endpoint) };
+        let endpoint = match raw_endpoint.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw endpoint failed: {}", e)).into())
+        };
+
+        let raw_auth_token = unsafe { CStr::from_ptr(c_auth_token) };
+        let auth_token = match raw_auth_token.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw auth_token failed: {}", e)).into())
+        };
+
+        let raw_message = unsafe { CStr::from_ptr(c_message) };
+        let message = match raw_message.to_str() {
+            Ok(s) => message_bytes(s),
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw message failed: {}", e)).into())
+        };
+
+        let raw_master_key_json = unsafe { CStr::from_ptr(c_master_key_json) };
+        let master_key_json = match raw_master_key_json.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw master_key_json failed: {}", e)).into())
+        };
+
+        let raw_path = unsafe { CStr::from_ptr(c_path) };
+        let path: DerivationPath = match raw_path.to_str() {
+            Ok(s) => match s.parse() {
+                Ok(p) => p,
+                Err(e) => return error_to_c_string(failure::Error::from(e))
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw path failed: {}", e)).into())
+        };
+
+        let raw_id = unsafe { CStr::from_ptr(c_id) };
+        let id = match raw_id.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw id failed: {}", e)).into())
+        };
+
+        let mk: MasterKey2 = match serde_json::from_str(master_key_json){
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::KeyFormat(format!("decoding master_key_json to MasterKey2 failed: {}", e)).into())
+        };
+
+        let mk_child = mk.get_child(path.to_bigints());
+
+        let client_shim = ClientShim::new(endpoint.to_string(), Some(auth_token.to_string()));
+
+        match personal_sign(&client_shim, &message, &mk_child, &path, id) {
+            Ok(signature) => success_to_c_string(signature),
+            Err(e) => {
+                error!("personal_sign failed: {}", e);
+                error_to_c_string(e)
+            }
+        }
+    })
+}
+
+#[test]
+fn test_personal_message_hash() {
+    assert_eq!(
+        hex::encode(personal_message_hash(b"Hello World")),
+        "a1de988600a42c4b4ab089b619297c17d53cffae5d5120d82d8a92d0bb3b78f2"
+    );
+    assert_eq!(message_bytes("0x48656c6c6f20576f726c64"), b"Hello World".to_vec());
+    assert_eq!(message_bytes("0xnot hex"), b"0xnot hex".to_vec());
+}
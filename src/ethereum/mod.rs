@@ -0,0 +1,266 @@
+//! Ethereum helpers on top of the two-party signature: addresses,
+//! transaction encoding and signing.
+
+use std::ffi::CStr;
+use std::fmt;
+use std::os::raw::c_char;
+use std::str::FromStr;
+
+use curv::arithmetic::traits::Converter;
+use curv::elliptic::curves::secp256_k1::GE;
+use curv::elliptic::curves::traits::ECPoint;
+use curv::BigInt;
+use kms::ecdsa::two_party::MasterKey2;
+use tiny_keccak::{Hasher, Keccak};
+
+use crate::ecdsa::{sign, DerivationPath};
+use crate::error::ClientError;
+use crate::utilities::{catch_panic, error_to_c_string, success_to_c_string, to_fixed_bytes};
+use crate::ClientShim;
+
+use super::Result;
+
+pub mod message;
+pub mod rlp;
+pub mod transaction;
+pub mod typed_data;
+
+pub use message::personal_sign;
+pub use transaction::{sign_transaction, SignedTransaction, Transaction};
+pub use typed_data::{sign_typed_data, TypedData};
+
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    hasher.update(data);
+    let mut out = [0u8; 32];
+    hasher.finalize(&mut out);
+    out
+}
+
+/// A 20 byte account address, displayed with the EIP-55 checksum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Address(pub [u8; 20]);
+
+impl Address {
+    /// The last 20 bytes of the Keccak-256 of the uncompressed public key.
+    pub fn from_public_key(q: &GE) -> Address {
+        let mut uncompressed = Vec::with_capacity(64);
+        uncompressed.extend_from_slice(&to_fixed_bytes(&q.x_coor().unwrap(), 32));
+        uncompressed.extend_from_slice(&to_fixed_bytes(&q.y_coor().unwrap(), 32));
+        let hash = keccak256(&uncompressed);
+        let mut address = [0u8; 20];
+        address.copy_from_slice(&hash[12..]);
+        Address(address)
+    }
+
+    pub fn to_checksum(&self) -> String {
+        let lower = hex::encode(self.0);
+        let hash = keccak256(lower.as_bytes());
+        let mut out = String::with_capacity(42);
+        out.push_str("0x");
+        for (i, c) in lower.chars().enumerate() {
+            let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+            if c.is_ascii_alphabetic() && nibble >= 8 {
+                out.push(c.to_ascii_uppercase());
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    }
+}
+
+impl FromStr for Address {
+    type Err = ClientError;
+
+    /// Accepts any case; a mixed-case address must carry a valid checksum.
+    fn from_str(s: &str) -> std::result::Result<Address, ClientError> {
+        let hex_part = s.strip_prefix("0x").unwrap_or(s);
+        let bytes = match hex::decode(hex_part) {
+            Ok(b) if b.len() == 20 => b,
+            _ => return Err(ClientError::InvalidInput(format!("invalid address {}", s)))
+        };
+        let mut address = [0u8; 20];
+        address.copy_from_slice(&bytes);
+        let address = Address(address);
+
+        let mixed_case = hex_part.chars().any(|c| c.is_ascii_lowercase()) && hex_part.chars().any(|c| c.is_ascii_uppercase());
+        if mixed_case && address.to_checksum()[2..] != *hex_part {
+            return Err(ClientError::InvalidInput(format!("bad checksum in address {}", s)))
+        }
+        Ok(address)
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_checksum())
+    }
+}
+
+impl serde::Serialize for Address {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Address {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Address, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Signs a 32 byte digest with `mk`, the child key already derived at
+/// `path`, and returns the `0x` hex of `r || s || v` with `v` 27 or 28.
+pub fn sign_digest(
+    client_shim: &ClientShim,
+    digest: &[u8; 32],
+    mk: &MasterKey2,
+    path: &DerivationPath,
+    id: &str,
+) -> Result<String> {
+    let message = BigInt::from_bytes(digest);
+    // `sign_with_path` already returns a low `s`, as EIP-2 requires
+    let signature = sign::sign_with_path(client_shim, message, mk, path, id)?;
+    if signature.recid > 1 {
+        return Err(ClientError::InvalidSignature(format!("recid {} can not be encoded in v", signature.recid)).into())
+    }
+    let mut out = to_fixed_bytes(&signature.r, 32);
+    out.extend(to_fixed_bytes(&signature.s, 32));
+    out.push(27 + signature.recid);
+    Ok(to_hex_prefixed(&out))
+}
+
+/// `0x` prefixed hex of `bytes`.
+pub(crate) fn to_hex_prefixed(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+pub(crate) fn from_hex_prefixed(s: &str) -> std::result::Result<Vec<u8>, ClientError> {
+    hex::decode(s.strip_prefix("0x").unwrap_or(s)).map_err(|e| ClientError::InvalidInput(format!("invalid hex {}: {}", s, e)))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct EthAddress {
+    address: Address,
+    path: DerivationPath,
+}
+
+/// Checksummed address of the child key at `c_path`, e.g. `m/44/60/0/0/0`.
+#[no_mangle]
+pub extern "C" fn eth_get_address(
+    c_master_key_json: *const c_char,//masterkey
+    c_path: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_master_key_json = unsafe { CStr::from_ptr(c_master_key_json) };
+        let master_key_json = match raw_master_key_json.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw master_key_json failed: {}", e)).into())
+        };
+
+        let raw_path = unsafe { CStr::from_ptr(c_path) };
+        let path: DerivationPath = match raw_path.to_str() {
+            Ok(s) => match s.parse() {
+                Ok(p) => p,
+                Err(e) => return error_to_c_string(failure::Error::from(e))
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw path failed: {}", e)).into())
+        };
+
+        let mk: MasterKey2 = match serde_json::from_str(master_key_json){
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::KeyFormat(format!("decoding master_key_json to MasterKey2 failed: {}", e)).into())
+        };
+
+        let mk_child = mk.get_child(path.to_bigints());
+
+        success_to_c_string(EthAddress {
+            address: Address::from_public_key(&mk_child.public.q),
+            path,
+        })
+    })
+}
+
+#[test]
+fn test_address() {
+    use curv::elliptic::curves::secp256_k1::FE;
+    use curv::elliptic::curves::traits::ECScalar;
+
+    // private key 1
+    let one: FE = ECScalar::from(&BigInt::from(1));
+    let g: GE = ECPoint::generator();
+    let address = Address::from_public_key(&(g * &one));
+    assert_eq!(address.to_string(), "0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf");
+
+    // EIP-55 examples
+    for a in &["0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed", "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359"] {
+        assert_eq!(a.parse::<Address>().unwrap().to_string(), *a);
+    }
+    assert!("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed".parse::<Address>().is_ok());
+    assert!("0x5AAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".parse::<Address>().is_err());
+    assert!("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA".parse::<Address>().is_err());
+}
+
+#[cfg(all(test, feature = "local-party-one"))]
+mod end_to_end {
+    use curv::arithmetic::traits::Converter;
+    use curv::elliptic::curves::secp256_k1::{FE, GE};
+    use curv::elliptic::curves::traits::{ECPoint, ECScalar};
+    use curv::BigInt;
+
+    use crate::ecdsa::server::test_share;
+
+    use super::message::personal_message_hash;
+    use super::{from_hex_prefixed, personal_sign, sign_transaction, Address, Transaction};
+
+    /// The signer of `digest` given `r || s || v`: `r^-1 (s R - z G)`.
+    fn recover_address(digest: &[u8; 32], signature: &str) -> Address {
+        let bytes = from_hex_prefixed(signature).unwrap();
+        let mut big_r = vec![0x02 | (bytes[64] - 27)];
+        big_r.extend_from_slice(&bytes[..32]);
+        let big_r = GE::from_bytes(&big_r).unwrap();
+
+        let r: FE = ECScalar::from(&BigInt::from_bytes(&bytes[..32]));
+        let s: FE = ECScalar::from(&BigInt::from_bytes(&bytes[32..64]));
+        let z: FE = ECScalar::from(&BigInt::from_bytes(digest));
+        let g: GE = ECPoint::generator();
+        let q = (big_r * &s).sub_point(&(g * &z).get_element()) * &r.invert();
+        Address::from_public_key(&q)
+    }
+
+    #[test]
+    fn test_sign_eth_transaction() {
+        let share = test_share("m/44/60/0/0/0");
+
+        let tx: Transaction = serde_json::from_str(r#"{
+            "type": "eip1559",
+            "chain_id": 1,
+            "nonce": 0,
+            "max_priority_fee_per_gas": "0x3b9aca00",
+            "max_fee_per_gas": "0x4a817c800",
+            "gas_limit": 21000,
+            "to": "0x3535353535353535353535353535353535353535",
+            "value": "1000000000000000000"
+        }"#).unwrap();
+
+        let signed = sign_transaction(&share.client_shim, &tx, &share.child, &share.path, &share.private_share.id).unwrap();
+        assert!(signed.raw_transaction.starts_with("0x02"));
+        assert!(signed.v <= 1);
+        assert_eq!(signed.from, Address::from_public_key(&share.child.public.q));
+    }
+
+    #[test]
+    fn test_personal_sign() {
+        let share = test_share("m/44/60/0/0/0");
+
+        let signature = personal_sign(&share.client_shim, b"Hello World", &share.child, &share.path, &share.private_share.id).unwrap();
+        assert_eq!(signature.len(), 2 + 65 * 2);
+        assert!(signature.ends_with("1b") || signature.ends_with("1c"));
+        assert_eq!(
+            recover_address(&personal_message_hash(b"Hello World"), &signature),
+            Address::from_public_key(&share.child.public.q)
+        );
+    }
+}
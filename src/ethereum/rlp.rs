@@ -0,0 +1,67 @@
+//! Minimal RLP encoder, enough for Ethereum transactions.
+
+/// An RLP item: a byte string or a list of items.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Rlp {
+    Bytes(Vec<u8>),
+    List(Vec<Rlp>),
+}
+
+impl Rlp {
+    /// A big-endian integer, with leading zeros stripped as RLP requires.
+    pub fn uint(be_bytes: &[u8]) -> Rlp {
+        let start = be_bytes.iter().position(|b| *b != 0).unwrap_or(be_bytes.len());
+        Rlp::Bytes(be_bytes[start..].to_vec())
+    }
+
+    pub fn u64(n: u64) -> Rlp {
+        Rlp::uint(&n.to_be_bytes())
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Rlp::Bytes(bytes) => {
+                if bytes.len() == 1 && bytes[0] < 0x80 {
+                    return bytes.clone()
+                }
+                let mut out = encode_length(bytes.len(), 0x80);
+                out.extend_from_slice(bytes);
+                out
+            }
+            Rlp::List(items) => {
+                let payload: Vec<u8> = items.iter().flat_map(|i| i.encode()).collect();
+                let mut out = encode_length(payload.len(), 0xc0);
+                out.extend_from_slice(&payload);
+                out
+            }
+        }
+    }
+}
+
+fn encode_length(len: usize, offset: u8) -> Vec<u8> {
+    if len < 56 {
+        return vec![offset + len as u8]
+    }
+    let len_bytes = (len as u64).to_be_bytes();
+    let start = len_bytes.iter().position(|b| *b != 0).unwrap();
+    let mut out = vec![offset + 55 + (8 - start) as u8];
+    out.extend_from_slice(&len_bytes[start..]);
+    out
+}
+
+#[test]
+fn test_rlp_encode() {
+    assert_eq!(Rlp::Bytes(b"dog".to_vec()).encode(), vec![0x83, b'd', b'o', b'g']);
+    assert_eq!(Rlp::Bytes(vec![]).encode(), vec![0x80]);
+    assert_eq!(Rlp::List(vec![]).encode(), vec![0xc0]);
+    assert_eq!(Rlp::u64(0).encode(), vec![0x80]);
+    assert_eq!(Rlp::u64(15).encode(), vec![0x0f]);
+    assert_eq!(Rlp::u64(1024).encode(), vec![0x82, 0x04, 0x00]);
+    assert_eq!(
+        Rlp::List(vec![Rlp::Bytes(b"cat".to_vec()), Rlp::Bytes(b"dog".to_vec())]).encode(),
+        vec![0xc8, 0x83, b'c', b'a', b't', 0x83, b'd', b'o', b'g']
+    );
+
+    let long = Rlp::Bytes(b"Lorem ipsum dolor sit amet, consectetur adipisicing elit".to_vec()).encode();
+    assert_eq!(&long[..2], &[0xb8, 0x38]);
+}
@@ -0,0 +1,450 @@
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+use curv::arithmetic::traits::Converter;
+use curv::BigInt;
+use kms::ecdsa::two_party::MasterKey2;
+use multi_party_ecdsa::protocols::two_party_ecdsa::lindell_2017::party_one;
+
+use crate::ecdsa::sign;
+use crate::ecdsa::DerivationPath;
+use crate::error::ClientError;
+use crate::utilities::{catch_panic, error_to_c_string, success_to_c_string, to_fixed_bytes};
+use crate::ClientShim;
+#[cfg(feature = "async")]
+use crate::AsyncClientShim;
+
+use super::super::Result;
+use super::rlp::Rlp;
+use super::{keccak256, to_hex_prefixed, Address};
+
+const EIP1559_TX_TYPE: u8 = 0x02;
+
+/// An unsigned transaction, as JSON:
+///
+/// `{"type": "eip1559", "chain_id": 1, "nonce": 0, "max_priority_fee_per_gas": "0x3b9aca00",
+///   "max_fee_per_gas": "0x4a817c800", "gas_limit": 21000, "to": "0x…", "value": "1000000000000000000"}`
+///
+/// Amounts are `0x` hex or decimal strings, `data` is `0x` hex and a missing
+/// `to` creates a contract.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Transaction {
+    Legacy(LegacyTransaction),
+    Eip1559(Eip1559Transaction),
+}
+
+/// A pre-EIP-2718 transaction; with a `chain_id` it is signed as EIP-155.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LegacyTransaction {
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+    pub nonce: u64,
+    #[serde(with = "quantity")]
+    pub gas_price: BigInt,
+    pub gas_limit: u64,
+    #[serde(default)]
+    pub to: Option<Address>,
+    #[serde(with = "quantity")]
+    pub value: BigInt,
+    #[serde(default, with = "hex_bytes")]
+    pub data: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Eip1559Transaction {
+    pub chain_id: u64,
+    pub nonce: u64,
+    #[serde(with = "quantity")]
+    pub max_priority_fee_per_gas: BigInt,
+    #[serde(with = "quantity")]
+    pub max_fee_per_gas: BigInt,
+    pub gas_limit: u64,
+    #[serde(default)]
+    pub to: Option<Address>,
+    #[serde(with = "quantity")]
+    pub value: BigInt,
+    #[serde(default, with = "hex_bytes")]
+    pub data: Vec<u8>,
+    #[serde(default)]
+    pub access_list: Vec<AccessListItem>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AccessListItem {
+    pub address: Address,
+    #[serde(with = "storage_keys")]
+    pub storage_keys: Vec<[u8; 32]>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SignedTransaction {
+    /// `0x` hex of the transaction ready for `eth_sendRawTransaction`.
+    pub raw_transaction: String,
+    pub transaction_hash: String,
+    pub from: Address,
+    pub v: u64,
+    pub r: String,
+    pub s: String,
+}
+
+impl Transaction {
+    /// The payload whose Keccak-256 is signed.
+    pub fn signing_payload(&self) -> Vec<u8> {
+        match self {
+            Transaction::Legacy(tx) => {
+                let mut fields = tx.fields();
+                if let Some(chain_id) = tx.chain_id {
+                    fields.extend(vec![Rlp::u64(chain_id), Rlp::u64(0), Rlp::u64(0)]);
+                }
+                Rlp::List(fields).encode()
+            }
+            Transaction::Eip1559(tx) => {
+                let mut out = vec![EIP1559_TX_TYPE];
+                out.extend(Rlp::List(tx.fields()).encode());
+                out
+            }
+        }
+    }
+
+    pub fn signing_hash(&self) -> [u8; 32] {
+        keccak256(&self.signing_payload())
+    }
+
+    /// The raw signed transaction. `signature` must already have a low `s`,
+    /// see `sign::normalize_s`.
+    pub fn encode_signed(&self, signature: &party_one::SignatureRecid) -> Result<Vec<u8>> {
+        let v = self.v(signature.recid)?;
+        let (r, s) = (Rlp::uint(&BigInt::to_vec(&signature.r)), Rlp::uint(&BigInt::to_vec(&signature.s)));
+        match self {
+            Transaction::Legacy(tx) => {
+                let mut fields = tx.fields();
+                fields.extend(vec![Rlp::u64(v), r, s]);
+                Ok(Rlp::List(fields).encode())
+            }
+            Transaction::Eip1559(tx) => {
+                let mut fields = tx.fields();
+                fields.extend(vec![Rlp::u64(v), r, s]);
+                let mut out = vec![EIP1559_TX_TYPE];
+                out.extend(Rlp::List(fields).encode());
+                Ok(out)
+            }
+        }
+    }
+
+    /// `v` for legacy transactions (EIP-155 when a chain id is set), the
+    /// y parity for typed ones.
+    fn v(&self, recid: u8) -> Result<u64> {
+        if recid > 1 {
+            return Err(ClientError::InvalidSignature(format!("recid {} can not be encoded in v", recid)).into())
+        }
+        let parity = recid as u64;
+        Ok(match self {
+            Transaction::Legacy(LegacyTransaction { chain_id: Some(chain_id), .. }) => chain_id * 2 + 35 + parity,
+            Transaction::Legacy(LegacyTransaction { chain_id: None, .. }) => 27 + parity,
+            Transaction::Eip1559(_) => parity,
+        })
+    }
+}
+
+impl LegacyTransaction {
+    fn fields(&self) -> Vec<Rlp> {
+        vec![
+            Rlp::u64(self.nonce),
+            Rlp::uint(&BigInt::to_vec(&self.gas_price)),
+            Rlp::u64(self.gas_limit),
+            to_rlp(&self.to),
+            Rlp::uint(&BigInt::to_vec(&self.value)),
+            Rlp::Bytes(self.data.clone()),
+        ]
+    }
+}
+
+impl Eip1559Transaction {
+    fn fields(&self) -> Vec<Rlp> {
+        let access_list = self.access_list.iter()
+            .map(|item| Rlp::List(vec![
+                Rlp::Bytes(item.address.0.to_vec()),
+                Rlp::List(item.storage_keys.iter().map(|k| Rlp::Bytes(k.to_vec())).collect()),
+            ]))
+            .collect();
+        vec![
+            Rlp::u64(self.chain_id),
+            Rlp::u64(self.nonce),
+            Rlp::uint(&BigInt::to_vec(&self.max_priority_fee_per_gas)),
+            Rlp::uint(&BigInt::to_vec(&self.max_fee_per_gas)),
+            Rlp::u64(self.gas_limit),
+            to_rlp(&self.to),
+            Rlp::uint(&BigInt::to_vec(&self.value)),
+            Rlp::Bytes(self.data.clone()),
+            Rlp::List(access_list),
+        ]
+    }
+}
+
+fn to_rlp(to: &Option<Address>) -> Rlp {
+    match to {
+        Some(address) => Rlp::Bytes(address.0.to_vec()),
+        None => Rlp::Bytes(vec![]),
+    }
+}
+
+fn signed_transaction(tx: &Transaction, mk: &MasterKey2, signature: party_one::SignatureRecid) -> Result<SignedTransaction> {
+    let raw = tx.encode_signed(&signature)?;
+    Ok(SignedTransaction {
+        transaction_hash: to_hex_prefixed(&keccak256(&raw)),
+        raw_transaction: to_hex_prefixed(&raw),
+        from: Address::from_public_key(&mk.public.q),
+        v: tx.v(signature.recid)?,
+        r: to_hex_prefixed(&to_fixed_bytes(&signature.r, 32)),
+        s: to_hex_prefixed(&to_fixed_bytes(&signature.s, 32)),
+    })
+}
+
+/// Signs `tx` with `mk`, the child key already derived at `path`.
+pub fn sign_transaction(
+    client_shim: &ClientShim,
+    tx: &Transaction,
+    mk: &MasterKey2,
+    path: &DerivationPath,
+    id: &str,
+) -> Result<SignedTransaction> {
+    let message = BigInt::from_bytes(&tx.signing_hash());
+    let signature = sign::sign_with_path(client_shim, message, mk, path, id)?;
+    signed_transaction(tx, mk, signature)
+}
+
+/// Async flavour of `sign_transaction`.
+#[cfg(feature = "async")]
+pub async fn sign_transaction_async(
+    client_shim: &AsyncClientShim,
+    tx: &Transaction,
+    mk: &MasterKey2,
+    path: &DerivationPath,
+    id: &str,
+) -> Result<SignedTransaction> {
+    let message = BigInt::from_bytes(&tx.signing_hash());
+    let signature = sign::sign_with_path_async(client_shim, message, mk, path, id).await?;
+    signed_transaction(tx, mk, signature)
+}
+
+/// Signs the `Transaction` JSON with the child key at `c_path` and returns a
+/// `SignedTransaction`.
+#[no_mangle]
+pub extern "C" fn eth_sign_transaction(
+    c_endpoint: *const c_char,
+    c_auth_token: *const c_char,
+    c_transaction_json: *const c_char,
+    c_master_key_json: *const c_char,//masterkey
+    c_path: *const c_char,//m/44/60/0/0/0
+    c_id: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_endpoint = unsafe { CStr::from_ptr(c_endpoint) };
+        let endpoint = match raw_endpoint.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw endpoint failed: {}", e)).into())
+        };
+
+        let raw_auth_token = unsafe { CStr::from_ptr(c_auth_token) };
+        let auth_token = match raw_auth_token.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw auth_token failed: {}", e)).into())
+        };
+
+        let raw_transaction_json = unsafe { CStr::from_ptr(c_transaction_json) };
+        let transaction_json = match raw_transaction_json.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw transaction_json failed: {}", e)).into())
+        };
+
+        let raw_master_key_json = unsafe { CStr::from_ptr(c_master_key_json) };
+        let master_key_json = match raw_master_key_json.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw master_key_json failed: {}", e)).into())
+        };
+
+        let raw_path = unsafe { CStr::from_ptr(c_path) };
+        let path: DerivationPath = match raw_path.to_str() {
+            Ok(s) => match s.parse() {
+                Ok(p) => p,
+                Err(e) => return error_to_c_string(failure::Error::from(e))
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw path failed: {}", e)).into())
+        };
+
+        let raw_id = unsafe { CStr::from_ptr(c_id) };
+        let id = match raw_id.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw id failed: {}", e)).into())
+        };
+
+        let tx: Transaction = match serde_json::from_str(transaction_json) {
+            Ok(tx) => tx,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding transaction_json failed: {}", e)).into())
+        };
+
+        let mk: MasterKey2 = match serde_json::from_str(master_key_json){
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::KeyFormat(format!("decoding master_key_json to MasterKey2 failed: {}", e)).into())
+        };
+
+        let mk_child = mk.get_child(path.to_bigints());
+
+        let client_shim = ClientShim::new(endpoint.to_string(), Some(auth_token.to_string()));
+
+        match sign_transaction(&client_shim, &tx, &mk_child, &path, id) {
+            Ok(signed) => success_to_c_string(signed),
+            Err(e) => {
+                error!("signing transaction failed: {}", e);
+                error_to_c_string(e)
+            }
+        }
+    })
+}
+
+/// Amounts as `0x` hex or decimal strings (or plain JSON numbers), written
+/// back as `0x` hex.
+mod quantity {
+    use curv::arithmetic::traits::Converter;
+    use curv::BigInt;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Number(u64),
+        Text(String),
+    }
+
+    pub fn serialize<S: Serializer>(n: &BigInt, serializer: S) -> Result<S::Ok, S::Error> {
+        let hex = hex::encode(BigInt::to_vec(n));
+        let hex = hex.trim_start_matches('0');
+        serializer.serialize_str(&format!("0x{}", if hex.is_empty() { "0" } else { hex }))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BigInt, D::Error> {
+        match Raw::deserialize(deserializer)? {
+            Raw::Number(n) => Ok(BigInt::from(n)),
+            Raw::Text(s) => parse(&s).ok_or_else(|| serde::de::Error::custom(format!("invalid quantity {}", s))),
+        }
+    }
+
+    fn parse(s: &str) -> Option<BigInt> {
+        if let Some(hex) = s.strip_prefix("0x") {
+            if hex.is_empty() {
+                return None
+            }
+            let padded = if hex.len() % 2 == 1 { format!("0{}", hex) } else { hex.to_string() };
+            return hex::decode(padded).ok().map(|b| BigInt::from_bytes(&b))
+        }
+        if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) {
+            return None
+        }
+        s.parse::<BigInt>().ok()
+    }
+}
+
+mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::to_hex_prefixed(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        super::super::from_hex_prefixed(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+mod storage_keys {
+    use serde::ser::SerializeSeq;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(keys: &[[u8; 32]], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(keys.len()))?;
+        for key in keys {
+            seq.serialize_element(&super::to_hex_prefixed(key))?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<[u8; 32]>, D::Error> {
+        let keys = Vec::<String>::deserialize(deserializer)?;
+        keys.iter().map(|s| {
+            let bytes = super::super::from_hex_prefixed(s).map_err(serde::de::Error::custom)?;
+            if bytes.len() != 32 {
+                return Err(serde::de::Error::custom(format!("storage key {} is not 32 bytes", s)))
+            }
+            let mut key = [0u8; 32];
+            key.copy_from_slice(&bytes);
+            Ok(key)
+        }).collect()
+    }
+}
+
+#[test]
+fn test_eip155_transaction() {
+    // the example from EIP-155
+    let tx: Transaction = serde_json::from_str(r#"{
+        "type": "legacy",
+        "chain_id": 1,
+        "nonce": 9,
+        "gas_price": "20000000000",
+        "gas_limit": 21000,
+        "to": "0x3535353535353535353535353535353535353535",
+        "value": "0xde0b6b3a7640000"
+    }"#).unwrap();
+
+    assert_eq!(
+        hex::encode(tx.signing_payload()),
+        "ec098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a764000080018080"
+    );
+    assert_eq!(
+        hex::encode(tx.signing_hash()),
+        "daf5a779ae972f972197303d7b574746c7ef83eadac0f2791ad23db92e4c8e53"
+    );
+
+    let signature = party_one::SignatureRecid {
+        r: BigInt::from_hex("28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276"),
+        s: BigInt::from_hex("67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"),
+        recid: 0,
+    };
+    assert_eq!(
+        hex::encode(tx.encode_signed(&signature).unwrap()),
+        "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+    );
+}
+
+#[test]
+fn test_eip1559_transaction() {
+    let tx: Transaction = serde_json::from_str(r#"{
+        "type": "eip1559",
+        "chain_id": 1,
+        "nonce": 0,
+        "max_priority_fee_per_gas": "0x3b9aca00",
+        "max_fee_per_gas": "0x4a817c800",
+        "gas_limit": 21000,
+        "to": "0x3535353535353535353535353535353535353535",
+        "value": "0",
+        "data": "0x"
+    }"#).unwrap();
+
+    let payload = tx.signing_payload();
+    assert_eq!(payload[0], EIP1559_TX_TYPE);
+    assert_eq!(
+        hex::encode(&payload[1..]),
+        "e80180843b9aca008504a817c8008252089435353535353535353535353535353535353535358080c0"
+    );
+
+    let signature = party_one::SignatureRecid { r: BigInt::from(1), s: BigInt::from(2), recid: 1 };
+    let raw = tx.encode_signed(&signature).unwrap();
+    assert_eq!(
+        hex::encode(&raw),
+        "02eb0180843b9aca008504a817c8008252089435353535353535353535353535353535353535358080c0010102"
+    );
+
+    let overflow = party_one::SignatureRecid { r: BigInt::from(1), s: BigInt::from(2), recid: 2 };
+    assert!(tx.encode_signed(&overflow).is_err());
+}
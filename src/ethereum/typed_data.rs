@@ -0,0 +1,426 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+
+use curv::arithmetic::traits::Converter;
+use curv::BigInt;
+use kms::ecdsa::two_party::MasterKey2;
+use serde_json::Value;
+
+use crate::ecdsa::DerivationPath;
+use crate::error::ClientError;
+use crate::utilities::{catch_panic, error_to_c_string, success_to_c_string, to_fixed_bytes};
+use crate::ClientShim;
+
+use super::super::Result;
+use super::{from_hex_prefixed, keccak256, sign_digest};
+
+const DOMAIN_TYPE: &str = "EIP712Domain";
+
+/// An EIP-712 document, as passed to `eth_signTypedData_v4`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TypedData {
+    pub types: BTreeMap<String, Vec<TypedDataField>>,
+    #[serde(rename = "primaryType")]
+    pub primary_type: String,
+    pub domain: Value,
+    #[serde(default)]
+    pub message: Value,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TypedDataField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+impl TypedData {
+    /// `keccak256(0x19 0x01 || domainSeparator || hashStruct(message))`.
+    pub fn digest(&self) -> std::result::Result<[u8; 32], ClientError> {
+        let mut data = vec![0x19, 0x01];
+        data.extend_from_slice(&self.hash_struct(DOMAIN_TYPE, &self.domain)?);
+        if self.primary_type != DOMAIN_TYPE {
+            data.extend_from_slice(&self.hash_struct(&self.primary_type, &self.message)?);
+        }
+        Ok(keccak256(&data))
+    }
+
+    pub fn hash_struct(&self, kind: &str, data: &Value) -> std::result::Result<[u8; 32], ClientError> {
+        Ok(keccak256(&self.encode_data(kind, data)?))
+    }
+
+    /// `Mail(Person from,Person to,string contents)Person(string name,address wallet)`
+    pub fn encode_type(&self, kind: &str) -> std::result::Result<String, ClientError> {
+        let mut deps = BTreeSet::new();
+        self.dependencies(kind, &mut deps)?;
+        deps.remove(kind);
+
+        let mut out = String::new();
+        for name in std::iter::once(kind).chain(deps.iter().map(|d| d.as_str())) {
+            let fields: Vec<String> = self.fields(name)?.iter().map(|f| format!("{} {}", f.kind, f.name)).collect();
+            out.push_str(&format!("{}({})", name, fields.join(",")));
+        }
+        Ok(out)
+    }
+
+    fn fields(&self, kind: &str) -> std::result::Result<&Vec<TypedDataField>, ClientError> {
+        self.types.get(kind).ok_or_else(|| ClientError::InvalidInput(format!("unknown type {}", kind)))
+    }
+
+    fn dependencies(&self, kind: &str, deps: &mut BTreeSet<String>) -> std::result::Result<(), ClientError> {
+        if deps.contains(kind) {
+            return Ok(())
+        }
+        deps.insert(kind.to_string());
+        for field in self.fields(kind)? {
+            let base = base_type(&field.kind);
+            if self.types.contains_key(base) {
+                self.dependencies(base, deps)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn encode_data(&self, kind: &str, data: &Value) -> std::result::Result<Vec<u8>, ClientError> {
+        let mut out = keccak256(self.encode_type(kind)?.as_bytes()).to_vec();
+        for field in self.fields(kind)? {
+            out.extend_from_slice(&self.encode_value(&field.kind, &data[field.name.as_str()])?);
+        }
+        Ok(out)
+    }
+
+    fn encode_value(&self, kind: &str, value: &Value) -> std::result::Result<[u8; 32], ClientError> {
+        if kind.ends_with(']') {
+            let (element, len) = array_type(kind)?;
+            let items = match value.as_array() {
+                Some(items) => items,
+                None => return Err(invalid(kind, value))
+            };
+            if len.map_or(false, |len| len != items.len()) {
+                return Err(ClientError::InvalidInput(format!("{} has {} elements", kind, items.len())))
+            }
+            let mut data = Vec::with_capacity(32 * items.len());
+            for item in items {
+                data.extend_from_slice(&self.encode_value(element, item)?);
+            }
+            return Ok(keccak256(&data))
+        }
+
+        if self.types.contains_key(kind) {
+            if value.is_null() {
+                return Ok([0u8; 32])
+            }
+            return self.hash_struct(kind, value)
+        }
+
+        match kind {
+            "string" => match value.as_str() {
+                Some(s) => Ok(keccak256(s.as_bytes())),
+                None => Err(invalid(kind, value))
+            },
+            "bytes" => match value.as_str() {
+                Some(s) => Ok(keccak256(&from_hex_prefixed(s)?)),
+                None => Err(invalid(kind, value))
+            },
+            "bool" => match value.as_bool() {
+                Some(b) => Ok(word(&[b as u8])),
+                None => Err(invalid(kind, value))
+            },
+            "address" => {
+                let bytes = match value.as_str() {
+                    Some(s) => from_hex_prefixed(s)?,
+                    None => return Err(invalid(kind, value))
+                };
+                if bytes.len() != 20 {
+                    return Err(invalid(kind, value))
+                }
+                Ok(word(&bytes))
+            }
+            _ if kind.starts_with("bytes") => {
+                let size: usize = kind[5..].parse().map_err(|_| ClientError::InvalidInput(format!("unknown type {}", kind)))?;
+                let bytes = match value.as_str() {
+                    Some(s) => from_hex_prefixed(s)?,
+                    None => return Err(invalid(kind, value))
+                };
+                if size == 0 || size > 32 || bytes.len() > size {
+                    return Err(invalid(kind, value))
+                }
+                let mut out = [0u8; 32];
+                out[..bytes.len()].copy_from_slice(&bytes);
+                Ok(out)
+            }
+            _ if kind.starts_with("uint") || kind.starts_with("int") => encode_integer(kind, value),
+            _ => Err(ClientError::InvalidInput(format!("unknown type {}", kind)))
+        }
+    }
+}
+
+/// `Person[][3]` -> `Person[]`, the element type of an array type.
+fn array_type(kind: &str) -> std::result::Result<(&str, Option<usize>), ClientError> {
+    let open = match kind.rfind('[') {
+        Some(i) => i,
+        None => return Err(ClientError::InvalidInput(format!("unknown type {}", kind)))
+    };
+    let len = &kind[open + 1..kind.len() - 1];
+    if len.is_empty() {
+        return Ok((&kind[..open], None))
+    }
+    match len.parse() {
+        Ok(len) => Ok((&kind[..open], Some(len))),
+        Err(_) => Err(ClientError::InvalidInput(format!("unknown type {}", kind)))
+    }
+}
+
+fn base_type(kind: &str) -> &str {
+    match kind.find('[') {
+        Some(i) => &kind[..i],
+        None => kind,
+    }
+}
+
+/// Numbers may be JSON numbers, decimal strings or `0x` hex strings;
+/// negative `intN` values are encoded as 256 bit two's complement. Values
+/// that do not fit in `N` bits are refused.
+fn encode_integer(kind: &str, value: &Value) -> std::result::Result<[u8; 32], ClientError> {
+    let bits = integer_bits(kind)?;
+    let (negative, magnitude) = match value {
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) => (false, BigInt::from(u)),
+            (None, Some(i)) => (true, BigInt::from(i.unsigned_abs())),
+            _ => return Err(invalid(kind, value))
+        },
+        Value::String(s) => {
+            let (negative, digits) = match s.strip_prefix('-') {
+                Some(rest) => (true, rest),
+                None => (false, s.as_str()),
+            };
+            let magnitude = if let Some(hex) = digits.strip_prefix("0x") {
+                let padded = if hex.len() % 2 == 1 { format!("0{}", hex) } else { hex.to_string() };
+                match hex::decode(padded) {
+                    Ok(bytes) if !hex.is_empty() => BigInt::from_bytes(&bytes),
+                    _ => return Err(invalid(kind, value))
+                }
+            } else if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+                match digits.parse::<BigInt>() {
+                    Ok(n) => n,
+                    Err(_) => return Err(invalid(kind, value))
+                }
+            } else {
+                return Err(invalid(kind, value))
+            };
+            (negative, magnitude)
+        }
+        _ => return Err(invalid(kind, value))
+    };
+
+    let out_of_range = if kind.starts_with("uint") {
+        negative || magnitude >= pow2(bits)
+    } else if negative {
+        magnitude > pow2(bits - 1)
+    } else {
+        magnitude >= pow2(bits - 1)
+    };
+    if out_of_range {
+        return Err(invalid(kind, value))
+    }
+    let zero = BigInt::from(0);
+    let n = if negative && magnitude != zero {
+        let mut modulus = vec![1u8];
+        modulus.extend_from_slice(&[0u8; 32]);
+        BigInt::from_bytes(&modulus) - magnitude
+    } else {
+        magnitude
+    };
+
+    let bytes = to_fixed_bytes(&n, 32);
+    let mut out = [0u8; 32];
+    out.copy_from_slice(&bytes);
+    Ok(out)
+}
+
+/// `N` of `uintN` or `intN`, 256 for `uint` and `int`.
+fn integer_bits(kind: &str) -> std::result::Result<usize, ClientError> {
+    let size = kind.trim_start_matches('u').trim_start_matches("int");
+    if size.is_empty() {
+        return Ok(256)
+    }
+    match size.parse::<usize>() {
+        Ok(bits) if bits > 0 && bits <= 256 && bits % 8 == 0 => Ok(bits),
+        _ => Err(ClientError::InvalidInput(format!("unknown type {}", kind)))
+    }
+}
+
+/// `2^bits`.
+fn pow2(bits: usize) -> BigInt {
+    let mut bytes = vec![1u8 << (bits % 8)];
+    bytes.extend(vec![0u8; bits / 8]);
+    BigInt::from_bytes(&bytes)
+}
+
+/// `bytes` left padded to a 32 byte word.
+fn word(bytes: &[u8]) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    out[32 - bytes.len()..].copy_from_slice(bytes);
+    out
+}
+
+fn invalid(kind: &str, value: &Value) -> ClientError {
+    ClientError::InvalidInput(format!("invalid {} value {}", kind, value))
+}
+
+/// `eth_signTypedData_v4` with `mk`, the child key already derived at `path`.
+pub fn sign_typed_data(
+    client_shim: &ClientShim,
+    typed_data: &TypedData,
+    mk: &MasterKey2,
+    path: &DerivationPath,
+    id: &str,
+) -> Result<String> {
+    let digest = typed_data.digest()?;
+    sign_digest(client_shim, &digest, mk, path, id)
+}
+
+/// `eth_signTypedData_v4` of the EIP-712 JSON `c_typed_data_json` with the
+/// child key at `c_path`; the result is the 65 byte signature as hex.
+#[no_mangle]
+pub extern "C" fn eth_sign_typed_data(
+    c_endpoint: *const c_char,
+    c_auth_token: *const c_char,
+    c_typed_data_json: *const c_char,
+    c_master_key_json: *const c_char,//masterkey
+    c_path: *const c_char,//m/44/60/0/0/0
+    c_id: *const c_char,
+) -> *mut c_char {
+    catch_panic(|| {
+        let raw_endpoint = unsafe { CStr::from_ptr(c_endpoint) };
+        let endpoint = match raw_endpoint.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw endpoint failed: {}", e)).into())
+        };
+
+        let raw_auth_token = unsafe { CStr::from_ptr(c_auth_token) };
+        let auth_token = match raw_auth_token.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw auth_token failed: {}", e)).into())
+        };
+
+        let raw_typed_data_json = unsafe { CStr::from_ptr(c_typed_data_json) };
+        let typed_data: TypedData = match raw_typed_data_json.to_str() {
+            Ok(s) => match serde_json::from_str(s) {
+                Ok(t) => t,
+                Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding typed_data_json failed: {}", e)).into())
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw typed_data_json failed: {}", e)).into())
+        };
+
+        let raw_master_key_json = unsafe { CStr::from_ptr(c_master_key_json) };
+        let master_key_json = match raw_master_key_json.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw master_key_json failed: {}", e)).into())
+        };
+
+        let raw_path = unsafe { CStr::from_ptr(c_path) };
+        let path: DerivationPath = match raw_path.to_str() {
+            Ok(s) => match s.parse() {
+                Ok(p) => p,
+                Err(e) => return error_to_c_string(failure::Error::from(e))
+            },
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw path failed: {}", e)).into())
+        };
+
+        let raw_id = unsafe { CStr::from_ptr(c_id) };
+        let id = match raw_id.to_str() {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::InvalidInput(format!("decoding raw id failed: {}", e)).into())
+        };
+
+        let mk: MasterKey2 = match serde_json::from_str(master_key_json){
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(ClientError::KeyFormat(format!("decoding master_key_json to MasterKey2 failed: {}", e)).into())
+        };
+
+        let mk_child = mk.get_child(path.to_bigints());
+
+        let client_shim = ClientShim::new(endpoint.to_string(), Some(auth_token.to_string()));
+
+        match sign_typed_data(&client_shim, &typed_data, &mk_child, &path, id) {
+            Ok(signature) => success_to_c_string(signature),
+            Err(e) => {
+                error!("signing typed data failed: {}", e);
+                error_to_c_string(e)
+            }
+        }
+    })
+}
+
+#[test]
+fn test_typed_data_digest() {
+    // the Mail example from EIP-712
+    let typed_data: TypedData = serde_json::from_str(r#"{
+        "types": {
+            "EIP712Domain": [
+                {"name": "name", "type": "string"},
+                {"name": "version", "type": "string"},
+                {"name": "chainId", "type": "uint256"},
+                {"name": "verifyingContract", "type": "address"}
+            ],
+            "Person": [
+                {"name": "name", "type": "string"},
+                {"name": "wallet", "type": "address"}
+            ],
+            "Mail": [
+                {"name": "from", "type": "Person"},
+                {"name": "to", "type": "Person"},
+                {"name": "contents", "type": "string"}
+            ]
+        },
+        "primaryType": "Mail",
+        "domain": {
+            "name": "Ether Mail",
+            "version": "1",
+            "chainId": 1,
+            "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+        },
+        "message": {
+            "from": {"name": "Cow", "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"},
+            "to": {"name": "Bob", "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"},
+            "contents": "Hello, Bob!"
+        }
+    }"#).unwrap();
+
+    assert_eq!(
+        typed_data.encode_type("Mail").unwrap(),
+        "Mail(Person from,Person to,string contents)Person(string name,address wallet)"
+    );
+    assert_eq!(
+        hex::encode(typed_data.hash_struct("EIP712Domain", &typed_data.domain).unwrap()),
+        "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+    );
+    assert_eq!(
+        hex::encode(typed_data.hash_struct("Mail", &typed_data.message).unwrap()),
+        "c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e"
+    );
+    assert_eq!(
+        hex::encode(typed_data.digest().unwrap()),
+        "be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2"
+    );
+
+    assert_eq!(encode_integer("int8", &serde_json::json!(-1)).unwrap(), [0xff; 32]);
+    assert_eq!(encode_integer("uint256", &serde_json::json!("0x10")).unwrap(), word(&[0x10]));
+    assert!(encode_integer("uint8", &serde_json::json!("-1")).is_err());
+
+    // values must fit in N bits
+    assert_eq!(encode_integer("uint8", &serde_json::json!(255)).unwrap(), word(&[0xff]));
+    assert!(encode_integer("uint8", &serde_json::json!(300)).is_err());
+    assert!(encode_integer("uint8", &serde_json::json!("0x100")).is_err());
+    assert_eq!(encode_integer("int8", &serde_json::json!(127)).unwrap(), word(&[0x7f]));
+    assert!(encode_integer("int8", &serde_json::json!(128)).is_err());
+    let mut min_int8 = [0xff; 32];
+    min_int8[31] = 0x80;
+    assert_eq!(encode_integer("int8", &serde_json::json!(-128)).unwrap(), min_int8);
+    assert!(encode_integer("int8", &serde_json::json!(-129)).is_err());
+    assert!(encode_integer("uint", &serde_json::json!(format!("0x1{}", "0".repeat(64)))).is_err());
+    assert!(encode_integer("int256", &serde_json::json!(format!("0x8{}", "0".repeat(63)))).is_err());
+    assert!(encode_integer("uint7", &serde_json::json!(1)).is_err());
+}
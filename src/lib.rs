@@ -7,6 +7,7 @@ extern crate kms;
 #[macro_use]
 extern crate log;
 extern crate multi_party_ecdsa;
+extern crate rand;
 extern crate reqwest;
 extern crate serde;
 #[macro_use]
@@ -17,26 +18,97 @@ extern crate alloc;
 
 pub use curv::{arithmetic::traits::Converter, BigInt};
 
+#[cfg(feature = "jni")]
+pub mod android;
+pub mod btc;
+pub mod chain;
 pub mod ecdsa;
+pub mod error;
+pub mod ethereum;
 pub mod utilities;
+pub mod version;
+
+pub use error::ClientError;
+pub use utilities::config::ClientConfig;
+pub use utilities::transport::{HttpTransport, Transport};
+#[cfg(feature = "async")]
+pub use utilities::async_transport::{AsyncHttpTransport, AsyncTransport};
 
 type Result<T> = std::result::Result<T, failure::Error>;
 
-#[derive(Debug)]
 pub struct ClientShim {
-    pub client: reqwest::Client,
-    pub auth_token: Option<String>,
-    pub endpoint: String,
+    pub transport: Box<dyn Transport>,
+    pub config: ClientConfig,
 }
 
 impl ClientShim {
+    /// A client with the default `ClientConfig`, timeouts included.
+    ///
+    /// Panics when the HTTP client can not be built (no TLS backend), as
+    /// `reqwest::blocking::Client::new` does.
     pub fn new(endpoint: String, auth_token: Option<String>) -> ClientShim {
-        let client = reqwest::Client::new();
+        match ClientShim::with_config(endpoint, auth_token, ClientConfig::default()) {
+            Ok(c) => c,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    pub fn with_config(endpoint: String, auth_token: Option<String>, config: ClientConfig) -> Result<ClientShim> {
+        let transport = HttpTransport::with_config(endpoint, auth_token, &config)?;
+        Ok(ClientShim {
+            transport: Box::new(transport),
+            config,
+        })
+    }
+
+    pub fn with_transport(transport: Box<dyn Transport>) -> ClientShim {
         ClientShim {
-            client,
-            auth_token,
-            endpoint,
+            transport,
+            config: ClientConfig::default(),
         }
     }
 }
 
+impl std::fmt::Debug for ClientShim {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ClientShim")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+/// Async counterpart of `ClientShim`, available with the `async` feature.
+#[cfg(feature = "async")]
+pub struct AsyncClientShim {
+    pub transport: Box<dyn AsyncTransport>,
+    pub config: ClientConfig,
+}
+
+#[cfg(feature = "async")]
+impl AsyncClientShim {
+    /// A client with the default `ClientConfig`, timeouts included.
+    ///
+    /// Panics when the HTTP client can not be built (no TLS backend), as
+    /// `reqwest::Client::new` does.
+    pub fn new(endpoint: String, auth_token: Option<String>) -> AsyncClientShim {
+        match AsyncClientShim::with_config(endpoint, auth_token, ClientConfig::default()) {
+            Ok(c) => c,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    pub fn with_config(endpoint: String, auth_token: Option<String>, config: ClientConfig) -> Result<AsyncClientShim> {
+        let transport = AsyncHttpTransport::with_config(endpoint, auth_token, &config)?;
+        Ok(AsyncClientShim {
+            transport: Box::new(transport),
+            config,
+        })
+    }
+
+    pub fn with_transport(transport: Box<dyn AsyncTransport>) -> AsyncClientShim {
+        AsyncClientShim {
+            transport,
+            config: ClientConfig::default(),
+        }
+    }
+}
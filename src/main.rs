@@ -1,3 +1,220 @@
+// Command-line client, for reproducing app issues and scripting key ceremonies
+#[macro_use]
+extern crate failure;
+#[macro_use]
+extern crate serde_derive;
+
+use std::fs;
+use std::io::Write;
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use curv::elliptic::curves::secp256_k1::GE;
+use curv::elliptic::curves::traits::ECPoint;
+use curv::BigInt;
+use multi_party_ecdsa::protocols::two_party_ecdsa::lindell_2017::party_one;
+
+use bw_mpc_client::ecdsa::key_derive::derive_child;
+use bw_mpc_client::ecdsa::{self, DerivationPath, PrivateShare};
+use bw_mpc_client::{ClientConfig, ClientShim, Converter};
+
+type Result<T> = std::result::Result<T, failure::Error>;
+
+#[derive(Serialize)]
+struct ExportedPublicKey {
+    id: String,
+    public_key: String,
+    q: GE,
+    chain_code: BigInt,
+}
+
 fn main() {
-    println!("Hello, world!");
+    let endpoint_args = [
+        Arg::with_name("endpoint").long("endpoint").takes_value(true).required(true)
+            .help("Party one server root, e.g. https://mpc.example.com"),
+        Arg::with_name("token").long("token").takes_value(true)
+            .help("Bearer token sent with every request"),
+        Arg::with_name("config").long("config").takes_value(true)
+            .help("ClientConfig JSON file (timeouts, retries)"),
+    ];
+    let share_arg = Arg::with_name("share").long("share").takes_value(true).required(true)
+        .help("PrivateShare JSON file");
+    let path_arg = Arg::with_name("path").long("path").takes_value(true).required(true)
+        .help("Derivation path, e.g. m/44/60/0/0/0");
+    let message_arg = Arg::with_name("message").long("message").takes_value(true).required(true)
+        .help("Message hash as hex");
+    let out_arg = Arg::with_name("out").long("out").takes_value(true)
+        .help("Output file, stdout if omitted");
+    // secret shares are never printed
+    let secret_out_arg = Arg::with_name("out").long("out").takes_value(true).required(true)
+        .help("Output file, created readable by the owner only");
+
+    let matches = App::new("mpc_mpc_client_exec")
+        .about("Two-party ECDSA client for the Bitverse MPC backend")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(SubCommand::with_name("keygen")
+            .about("Runs a keygen and writes the PrivateShare")
+            .args(&endpoint_args)
+            .arg(secret_out_arg.clone()))
+        .subcommand(SubCommand::with_name("derive")
+            .about("Writes the child PrivateShare at the path")
+            .arg(share_arg.clone())
+            .arg(path_arg.clone())
+            .arg(secret_out_arg.clone()))
+        .subcommand(SubCommand::with_name("pubkey")
+            .about("Prints the compressed public key, optionally at a path")
+            .arg(share_arg.clone())
+            .arg(path_arg.clone().required(false)))
+        .subcommand(SubCommand::with_name("sign")
+            .about("Signs a message hash with the child key at the path")
+            .args(&endpoint_args)
+            .arg(share_arg.clone())
+            .arg(path_arg.clone())
+            .arg(message_arg.clone())
+            .arg(out_arg.clone()))
+        .subcommand(SubCommand::with_name("verify")
+            .about("Verifies a SignatureRecid against the child key at the path")
+            .arg(share_arg.clone())
+            .arg(path_arg.clone())
+            .arg(message_arg.clone())
+            .arg(Arg::with_name("signature").long("signature").takes_value(true).required(true)
+                .help("SignatureRecid JSON file")))
+        .subcommand(SubCommand::with_name("export")
+            .about("Prints the public part of a PrivateShare (no secret material)")
+            .arg(share_arg.clone())
+            .arg(out_arg.clone()))
+        .get_matches();
+
+    let result = match matches.subcommand() {
+        ("keygen", Some(m)) => keygen(m),
+        ("derive", Some(m)) => derive(m),
+        ("pubkey", Some(m)) => pubkey(m),
+        ("sign", Some(m)) => sign(m),
+        ("verify", Some(m)) => verify(m),
+        ("export", Some(m)) => export(m),
+        _ => unreachable!(),
+    };
+
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn keygen(m: &ArgMatches) -> Result<()> {
+    let client_shim = client_shim(m)?;
+    let private_share = ecdsa::get_master_key(&client_shim)?;
+    write_secret(m, &serde_json::to_string_pretty(&private_share)?)
+}
+
+fn derive(m: &ArgMatches) -> Result<()> {
+    let (private_share, path) = (read_share(m)?, path(m)?);
+    let child = PrivateShare {
+        id: private_share.id.clone(),
+        master_key: derive_child(&private_share.master_key, &path),
+    };
+    write_secret(m, &serde_json::to_string_pretty(&child)?)
+}
+
+fn pubkey(m: &ArgMatches) -> Result<()> {
+    let private_share = read_share(m)?;
+    let q = match m.value_of("path") {
+        Some(_) => derive_child(&private_share.master_key, &path(m)?).public.q,
+        None => private_share.master_key.public.q,
+    };
+    println!("{}", compressed_hex(&q));
+    Ok(())
+}
+
+fn sign(m: &ArgMatches) -> Result<()> {
+    let client_shim = client_shim(m)?;
+    let (private_share, path) = (read_share(m)?, path(m)?);
+    let message = hex_to_bigint(m.value_of("message").unwrap())?;
+
+    let child = derive_child(&private_share.master_key, &path);
+    let signature = ecdsa::sign::sign_with_path(&client_shim, message, &child, &path, &private_share.id)?;
+    write_output(m, &serde_json::to_string_pretty(&signature)?)
+}
+
+fn verify(m: &ArgMatches) -> Result<()> {
+    let (private_share, path) = (read_share(m)?, path(m)?);
+    let message = hex_to_bigint(m.value_of("message").unwrap())?;
+    let signature: party_one::SignatureRecid =
+        serde_json::from_str(&fs::read_to_string(m.value_of("signature").unwrap())?)?;
+
+    // the same range, zero-message and recid checks as after signing
+    let child = derive_child(&private_share.master_key, &path);
+    ecdsa::sign::verify_signature(&signature, &child.public.q, &message)?;
+    println!("OK");
+    Ok(())
+}
+
+fn export(m: &ArgMatches) -> Result<()> {
+    let private_share = read_share(m)?;
+    let exported = ExportedPublicKey {
+        id: private_share.id.clone(),
+        public_key: compressed_hex(&private_share.master_key.public.q),
+        q: private_share.master_key.public.q,
+        chain_code: private_share.master_key.chain_code.clone(),
+    };
+    write_output(m, &serde_json::to_string_pretty(&exported)?)
+}
+
+fn client_shim(m: &ArgMatches) -> Result<ClientShim> {
+    let endpoint = m.value_of("endpoint").unwrap().to_string();
+    let token = m.value_of("token").map(|t| t.to_string());
+    let config: ClientConfig = match m.value_of("config") {
+        Some(file) => serde_json::from_str(&fs::read_to_string(file)?)?,
+        None => ClientConfig::default(),
+    };
+    ClientShim::with_config(endpoint, token, config)
+}
+
+fn read_share(m: &ArgMatches) -> Result<PrivateShare> {
+    let file = m.value_of("share").unwrap();
+    Ok(serde_json::from_str(&fs::read_to_string(file)?)?)
+}
+
+fn path(m: &ArgMatches) -> Result<DerivationPath> {
+    Ok(m.value_of("path").unwrap().parse()?)
+}
+
+fn hex_to_bigint(hex: &str) -> Result<BigInt> {
+    let hex = hex.trim_start_matches("0x");
+    Ok(serde_json::from_value(serde_json::Value::String(hex.to_string()))?)
+}
+
+fn compressed_hex(q: &GE) -> String {
+    format!("{:0>66}", q.bytes_compressed_to_big_int().to_hex())
+}
+
+fn write_output(m: &ArgMatches, content: &str) -> Result<()> {
+    match m.value_of("out") {
+        Some(file) => Ok(fs::write(file, content)?),
+        None => {
+            println!("{}", content);
+            Ok(())
+        }
+    }
+}
+
+/// Writes a `PrivateShare` to `--out`, with mode 0600 on unix so other users
+/// can not read it.
+fn write_secret(m: &ArgMatches, content: &str) -> Result<()> {
+    let file = m.value_of("out").unwrap();
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut out = options.open(file)?;
+    // `mode` only applies to a new file
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        out.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    out.write_all(content.as_bytes())?;
+    Ok(())
 }
@@ -0,0 +1,55 @@
+use std::time::Instant;
+
+use floating_duration::TimeFormat;
+use serde;
+
+use super::requests::{decode_reply, encode_body, retry_delay};
+
+use super::super::AsyncClientShim;
+use super::super::Result;
+
+pub async fn post<V>(client_shim: &AsyncClientShim, path: &str) -> Result<V>
+    where V: serde::de::DeserializeOwned
+{
+    _postb(client_shim, path, "{}").await
+}
+
+pub async fn postb<T, V>(client_shim: &AsyncClientShim, path: &str, body: T) -> Result<V>
+    where
+        T: serde::ser::Serialize,
+        V: serde::de::DeserializeOwned
+{
+    _postb(client_shim, path, body).await
+}
+
+async fn _postb<T, V>(client_shim: &AsyncClientShim, path: &str, body: T) -> Result<V>
+    where
+        T: serde::ser::Serialize,
+        V: serde::de::DeserializeOwned
+{
+    let start = Instant::now();
+
+    let body = encode_body(path, body)?;
+
+    let value = _post_with_retry(client_shim, path, body).await?;
+
+    info!("(req {}, took: {})", path, TimeFormat(start.elapsed()));
+
+    decode_reply(&value)
+}
+
+async fn _post_with_retry(client_shim: &AsyncClientShim, path: &str, body: String) -> Result<String> {
+    let mut attempt = 0;
+    loop {
+        let err = match client_shim.transport.post(path, body.clone()).await {
+            Ok(v) => return Ok(v),
+            Err(e) => e,
+        };
+
+        match retry_delay(&client_shim.config, path, &err, attempt) {
+            Some(delay) => tokio::time::sleep(delay).await,
+            None => return Err(err),
+        }
+        attempt += 1;
+    }
+}
@@ -0,0 +1,70 @@
+use async_trait::async_trait;
+
+use crate::error::ClientError;
+
+use super::config::ClientConfig;
+use super::transport::{endpoint_url, http_reply};
+
+use super::super::Result;
+
+/// Async counterpart of `Transport`, for services driving many MPC sessions
+/// on one tokio runtime.
+#[async_trait]
+pub trait AsyncTransport: Send + Sync {
+    async fn post(&self, path: &str, body: String) -> Result<String>;
+}
+
+/// JSON over HTTP with the async `reqwest::Client`.
+pub struct AsyncHttpTransport {
+    client: reqwest::Client,
+    endpoint: String,
+    auth_token: Option<String>,
+}
+
+impl AsyncHttpTransport {
+    pub fn new(endpoint: String, auth_token: Option<String>) -> AsyncHttpTransport {
+        AsyncHttpTransport {
+            client: reqwest::Client::new(),
+            endpoint,
+            auth_token,
+        }
+    }
+
+    pub fn with_config(endpoint: String, auth_token: Option<String>, config: &ClientConfig) -> Result<AsyncHttpTransport> {
+        let client = match reqwest::Client::builder()
+            .connect_timeout(config.connect_timeout())
+            .timeout(config.request_timeout())
+            .build() {
+            Ok(c) => c,
+            Err(e) => return Err(ClientError::System(format!("building http client failed: {}", e)).into())
+        };
+        Ok(AsyncHttpTransport {
+            client,
+            endpoint,
+            auth_token,
+        })
+    }
+}
+
+#[async_trait]
+impl AsyncTransport for AsyncHttpTransport {
+    async fn post(&self, path: &str, body: String) -> Result<String> {
+        let mut b = self
+            .client
+            .post(endpoint_url(&self.endpoint, path)?)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body);
+
+        if let Some(auth_token) = &self.auth_token {
+            b = b.bearer_auth(auth_token);
+        }
+
+        let res = match b.send().await {
+            Ok(r) => r,
+            Err(e) => return Err(ClientError::Network(format!("{}", e)).into())
+        };
+
+        let status = res.status();
+        http_reply(status, res.text().await)
+    }
+}
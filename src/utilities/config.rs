@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Network behaviour of a `ClientShim`.
+///
+/// Every field has a default, so a JSON config only needs the fields it
+/// overrides, e.g. `{"request_timeout_ms": 15000, "max_retries": 3}`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ClientConfig {
+    /// Timeout for establishing the TCP/TLS connection.
+    pub connect_timeout_ms: u64,
+    /// Timeout for a whole request, from sending to reading the reply body.
+    pub request_timeout_ms: u64,
+    /// How many times a failed request to an idempotent endpoint is repeated.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on every further attempt.
+    pub backoff_base_ms: u64,
+    /// Upper bound for a single backoff delay.
+    pub backoff_max_ms: u64,
+    /// Path suffixes of the endpoints that may be safely repeated.
+    pub idempotent_paths: Vec<String>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> ClientConfig {
+        ClientConfig {
+            connect_timeout_ms: 10_000,
+            request_timeout_ms: 30_000,
+            max_retries: 2,
+            backoff_base_ms: 200,
+            backoff_max_ms: 5_000,
+            // party one overwrites the ephemeral key of a repeated first sign message
+            idempotent_paths: vec!["ecdsa/sign/first".to_string()],
+        }
+    }
+}
+
+impl ClientConfig {
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_millis(self.connect_timeout_ms)
+    }
+
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_millis(self.request_timeout_ms)
+    }
+
+    pub fn is_idempotent(&self, path: &str) -> bool {
+        self.idempotent_paths.iter().any(|p| path.ends_with(p.as_str()))
+    }
+
+    /// Exponential backoff with jitter: a random delay between half and the
+    /// whole of `backoff_base_ms * 2^attempt`, capped at `backoff_max_ms`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self.backoff_base_ms.saturating_mul(1u64 << attempt.min(32));
+        let delay = exp.min(self.backoff_max_ms);
+        if delay == 0 {
+            return Duration::from_millis(0)
+        }
+        let jittered = rand::thread_rng().gen_range(delay / 2, delay + 1);
+        Duration::from_millis(jittered)
+    }
+}
+
+#[test]
+fn test_client_config_defaults() {
+    let config: ClientConfig = serde_json::from_str(r#"{"max_retries": 5}"#).unwrap();
+    assert_eq!(config.max_retries, 5);
+    assert_eq!(config.request_timeout_ms, 30_000);
+    assert!(config.is_idempotent("bitverse/wallet/v1/private/mpc/ecdsa/sign/first"));
+    assert!(!config.is_idempotent("bitverse/wallet/v1/private/mpc/ecdsa/sign/second"));
+    assert!(config.backoff(10) <= Duration::from_millis(config.backoff_max_ms));
+}
+
+#[test]
+fn test_backoff_bounds() {
+    let config = ClientConfig {
+        backoff_base_ms: 100,
+        backoff_max_ms: 1_000,
+        ..ClientConfig::default()
+    };
+    for attempt in 0..40 {
+        let full = (100u64 << attempt.min(32)).min(1_000);
+        for _ in 0..20 {
+            let delay = config.backoff(attempt);
+            assert!(delay <= Duration::from_millis(config.backoff_max_ms), "attempt {}: {:?}", attempt, delay);
+            assert!(delay >= Duration::from_millis(full / 2), "attempt {}: {:?}", attempt, delay);
+        }
+    }
+
+    let no_backoff = ClientConfig { backoff_base_ms: 0, ..ClientConfig::default() };
+    assert_eq!(no_backoff.backoff(3), Duration::from_millis(0));
+}
@@ -1,8 +1,20 @@
+use std::any::Any;
 use std::ffi::CString;
 use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use curv::arithmetic::traits::Converter;
+use curv::BigInt;
 use serde::Serialize;
 
+use crate::error::{error_code, http_status, ClientError};
+
+pub mod config;
 pub mod requests;
+pub mod transport;
+#[cfg(feature = "async")]
+pub mod async_requests;
+#[cfg(feature = "async")]
+pub mod async_transport;
 
 pub const SYSTEM_ERROR_CODE: i32 = 10104000;
 pub const SUCCESS_CODE: i32 = 0;
@@ -10,9 +22,10 @@ pub const SUCCESS_CODE: i32 = 0;
 
 pub fn error_to_c_string_whith_code(err_code: i32,e: failure::Error) -> *mut c_char {
     let c_result = CResult {
-        ret_code: SYSTEM_ERROR_CODE,
+        ret_code: err_code,
         ret_msg: format!("Error: {}", e.to_string()),
         result: "".to_string(),
+        http_status: http_status(&e),
     };
 
     let c_result_str = serde_json::to_string(&c_result).unwrap();
@@ -20,7 +33,22 @@ pub fn error_to_c_string_whith_code(err_code: i32,e: failure::Error) -> *mut c_c
 }
 
 pub fn error_to_c_string(e: failure::Error) -> *mut c_char {
-    error_to_c_string_whith_code(SYSTEM_ERROR_CODE,e)
+    let err_code = error_code(&e);
+    error_to_c_string_whith_code(err_code,e)
+}
+
+/// Like `error_to_c_string`, for the few calls whose state changed before
+/// they failed and has to be stored anyway: `result` holds `T` as JSON.
+pub fn error_with_result_to_c_string<T: Serialize>(e: failure::Error, result: T) -> *mut c_char {
+    let c_result = CResult {
+        ret_code: error_code(&e),
+        ret_msg: format!("Error: {}", e.to_string()),
+        result: serde_json::to_string(&result).unwrap(),
+        http_status: http_status(&e),
+    };
+
+    let c_result_str = serde_json::to_string(&c_result).unwrap();
+    CString::new(c_result_str.to_owned()).unwrap().into_raw()
 }
 
 pub fn success_to_c_string<T: Serialize>(result: T) -> *mut c_char {
@@ -28,15 +56,69 @@ pub fn success_to_c_string<T: Serialize>(result: T) -> *mut c_char {
         ret_code: SUCCESS_CODE,
         ret_msg: "OK".to_string(),
         result: serde_json::to_string(&result).unwrap(),
+        http_status: None,
     };
 
     let c_result_str = serde_json::to_string(&c_result).unwrap();
     CString::new(c_result_str.to_owned()).unwrap().into_raw()
 }
 
+/// Runs the body of an FFI entry point, turning a panic into a `CResult`
+/// error instead of unwinding into the host app.
+pub fn catch_panic<F>(f: F) -> *mut c_char
+    where F: FnOnce() -> *mut c_char
+{
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(r) => r,
+        Err(cause) => error_to_c_string(ClientError::System(format!("panic: {}", panic_message(&cause))).into()),
+    }
+}
+
+fn panic_message(cause: &Box<dyn Any + Send>) -> String {
+    if let Some(s) = cause.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = cause.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown".to_string()
+    }
+}
+
+/// Big-endian bytes of `n`, left padded to `len`.
+pub fn to_fixed_bytes(n: &BigInt, len: usize) -> Vec<u8> {
+    let bytes = BigInt::to_vec(n);
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    let bytes = &bytes[start..];
+    let mut out = vec![0u8; len.saturating_sub(bytes.len())];
+    out.extend_from_slice(bytes);
+    out
+}
+
 #[derive(Serialize)]
 pub struct CResult {
     ret_code: i32,
     ret_msg: String,
     result: String,
-}
\ No newline at end of file
+    /// Only with `HTTP_STATUS_CODE`, so bindings can retry on 5xx and 429.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    http_status: Option<u16>,
+}
+
+#[test]
+fn test_catch_panic() {
+    let rt = catch_panic(|| panic!("boom"));
+    let c_result = unsafe { CString::from_raw(rt) };
+    let c_result: serde_json::Value = serde_json::from_str(c_result.to_str().unwrap()).unwrap();
+    assert_eq!(c_result["ret_code"], SYSTEM_ERROR_CODE);
+    assert!(c_result["ret_msg"].as_str().unwrap().contains("boom"));
+    assert!(c_result.get("http_status").is_none());
+}
+
+#[test]
+fn test_http_status_in_c_result() {
+    let rt = error_to_c_string(ClientError::Http { status: 503, body: "busy".to_string() }.into());
+    let c_result = unsafe { CString::from_raw(rt) };
+    let c_result: serde_json::Value = serde_json::from_str(c_result.to_str().unwrap()).unwrap();
+    assert_eq!(c_result["ret_code"], crate::error::HTTP_STATUS_CODE);
+    assert_eq!(c_result["http_status"], 503);
+}
@@ -1,17 +1,22 @@
-use std::time::Instant;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use floating_duration::TimeFormat;
 use serde;
 
+use crate::error::{body_snippet, ClientError};
+use crate::ClientConfig;
+
 use super::super::ClientShim;
+use super::super::Result;
 
-pub fn post<V>(client_shim: &ClientShim, path: &str) -> Option<V>
+pub fn post<V>(client_shim: &ClientShim, path: &str) -> Result<V>
     where V: serde::de::DeserializeOwned
 {
     _postb(client_shim, path, "{}")
 }
 
-pub fn postb<T, V>(client_shim: &ClientShim, path: &str, body: T) -> Option<V>
+pub fn postb<T, V>(client_shim: &ClientShim, path: &str, body: T) -> Result<V>
     where
         T: serde::ser::Serialize,
         V: serde::de::DeserializeOwned
@@ -19,29 +24,120 @@ pub fn postb<T, V>(client_shim: &ClientShim, path: &str, body: T) -> Option<V>
     _postb(client_shim, path, body)
 }
 
-fn _postb<T, V>(client_shim: &ClientShim, path: &str, body: T) -> Option<V>
+fn _postb<T, V>(client_shim: &ClientShim, path: &str, body: T) -> Result<V>
     where
         T: serde::ser::Serialize,
         V: serde::de::DeserializeOwned
 {
     let start = Instant::now();
 
-    let mut b = client_shim
-        .client
-        .post(&format!("{}/{}", client_shim.endpoint, path));
+    let body = encode_body(path, body)?;
+
+    let value = _post_with_retry(client_shim, path, body)?;
+
+    info!("(req {}, took: {})", path, TimeFormat(start.elapsed()));
+
+    decode_reply(&value)
+}
+
+fn _post_with_retry(client_shim: &ClientShim, path: &str, body: String) -> Result<String> {
+    let mut attempt = 0;
+    loop {
+        let err = match client_shim.transport.post(path, body.clone()) {
+            Ok(v) => return Ok(v),
+            Err(e) => e,
+        };
 
-    if client_shim.auth_token.is_some() {
-        b = b.bearer_auth(client_shim.auth_token.clone().unwrap());
+        match retry_delay(&client_shim.config, path, &err, attempt) {
+            Some(delay) => thread::sleep(delay),
+            None => return Err(err),
+        }
+        attempt += 1;
     }
+}
 
-    let res = b.json(&body).send();
+pub(crate) fn encode_body<T>(path: &str, body: T) -> Result<String>
+    where T: serde::ser::Serialize
+{
+    match serde_json::to_string(&body) {
+        Ok(b) => Ok(b),
+        Err(e) => Err(ClientError::InvalidInput(format!("encoding request for {} failed: {}", path, e)).into())
+    }
+}
 
-    info!("(req {}, took: {})", path, TimeFormat(start.elapsed()));
+pub(crate) fn decode_reply<V>(value: &str) -> Result<V>
+    where V: serde::de::DeserializeOwned
+{
+    match serde_json::from_str(value) {
+        Ok(v) => Ok(v),
+        Err(e) => Err(ClientError::MalformedResponse(format!("{} (body: {})", e, body_snippet(value))).into())
+    }
+}
 
-    let value = match res {
-        Ok(mut v) => v.text().unwrap(),
-        Err(_) => return None
+/// How long to wait before repeating a failed request, or `None` when the
+/// failure must be reported: the error is not transient, the endpoint is not
+/// idempotent, or the retries are used up.
+pub(crate) fn retry_delay(config: &ClientConfig, path: &str, err: &failure::Error, attempt: u32) -> Option<Duration> {
+    let max_retries = if config.is_idempotent(path) { config.max_retries } else { 0 };
+
+    let retryable = match err.downcast_ref::<ClientError>() {
+        Some(e) => e.is_retryable(),
+        None => false,
+    };
+    if !retryable || attempt >= max_retries {
+        warn!("(req {} failed after {} attempts: {})", path, attempt + 1, err);
+        return None
+    }
+
+    let delay = config.backoff(attempt);
+    warn!("(req {} failed: {}, retrying in {:?})", path, err, delay);
+    Some(delay)
+}
+
+#[cfg(test)]
+struct FailingTransport {
+    calls: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    error: fn() -> ClientError,
+}
+
+#[cfg(test)]
+impl crate::Transport for FailingTransport {
+    fn post(&self, _path: &str, _body: String) -> Result<String> {
+        self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        Err((self.error)().into())
+    }
+}
+
+#[cfg(test)]
+fn attempts(error: fn() -> ClientError, path: &str) -> usize {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mut client_shim = ClientShim::with_transport(Box::new(FailingTransport { calls: calls.clone(), error }));
+    client_shim.config = ClientConfig {
+        max_retries: 3,
+        backoff_base_ms: 0,
+        idempotent_paths: vec!["ecdsa/sign/first".to_string()],
+        ..ClientConfig::default()
     };
 
-    Some(serde_json::from_str(value.as_str()).unwrap())
-}
\ No newline at end of file
+    let reply: Result<serde_json::Value> = postb(&client_shim, path, serde_json::json!({}));
+    assert!(reply.is_err());
+    calls.load(Ordering::SeqCst)
+}
+
+#[test]
+fn test_retry_policy() {
+    let network = || ClientError::Network("connection reset".to_string());
+
+    // the first attempt plus max_retries repeats
+    assert_eq!(attempts(network, "bitverse/wallet/v1/private/mpc/ecdsa/sign/first"), 4);
+    // only idempotent paths are repeated
+    assert_eq!(attempts(network, "bitverse/wallet/v1/private/mpc/ecdsa/sign/second"), 1);
+    assert_eq!(attempts(network, "bitverse/wallet/v1/private/mpc/ecdsa/keygen/first"), 1);
+    // and only on transient errors
+    assert_eq!(attempts(|| ClientError::InvalidInput("bad".to_string()), "bitverse/wallet/v1/private/mpc/ecdsa/sign/first"), 1);
+    assert_eq!(attempts(|| ClientError::Http { status: 503, body: String::new() }, "bitverse/wallet/v1/private/mpc/ecdsa/sign/first"), 4);
+    assert_eq!(attempts(|| ClientError::Http { status: 400, body: String::new() }, "bitverse/wallet/v1/private/mpc/ecdsa/sign/first"), 1);
+}
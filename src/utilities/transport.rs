@@ -0,0 +1,108 @@
+use reqwest::Url;
+
+use crate::error::{body_snippet, ClientError};
+
+use super::config::ClientConfig;
+
+use super::super::Result;
+
+/// Carries one request of the MPC protocol to party one and returns its reply.
+///
+/// `path` is relative to the server root (for example
+/// `bitverse/wallet/v1/private/mpc/ecdsa/sign/first`) and `body` is the JSON
+/// encoded request. Implementations return the raw reply body, which the
+/// protocol code decodes into a `ServerReply`.
+pub trait Transport: Send + Sync {
+    fn post(&self, path: &str, body: String) -> Result<String>;
+}
+
+/// Default transport: JSON over HTTP with a blocking `reqwest::blocking::Client`.
+pub struct HttpTransport {
+    client: reqwest::blocking::Client,
+    endpoint: String,
+    auth_token: Option<String>,
+}
+
+impl HttpTransport {
+    pub fn new(endpoint: String, auth_token: Option<String>) -> HttpTransport {
+        HttpTransport {
+            client: reqwest::blocking::Client::new(),
+            endpoint,
+            auth_token,
+        }
+    }
+
+    /// Builds a transport whose connect and request timeouts follow `config`.
+    pub fn with_config(endpoint: String, auth_token: Option<String>, config: &ClientConfig) -> Result<HttpTransport> {
+        let client = match reqwest::blocking::Client::builder()
+            .connect_timeout(config.connect_timeout())
+            .timeout(config.request_timeout())
+            .build() {
+            Ok(c) => c,
+            Err(e) => return Err(ClientError::System(format!("building http client failed: {}", e)).into())
+        };
+        Ok(HttpTransport {
+            client,
+            endpoint,
+            auth_token,
+        })
+    }
+
+    fn url(&self, path: &str) -> Result<Url> {
+        endpoint_url(&self.endpoint, path)
+    }
+}
+
+pub(crate) fn endpoint_url(endpoint: &str, path: &str) -> Result<Url> {
+    let base = match Url::parse(&format!("{}/", endpoint.trim_end_matches('/'))) {
+        Ok(u) => u,
+        Err(e) => return Err(ClientError::InvalidInput(format!("invalid endpoint {}: {}", endpoint, e)).into())
+    };
+    match base.join(path.trim_start_matches('/')) {
+        Ok(u) => Ok(u),
+        Err(e) => Err(ClientError::InvalidInput(format!("invalid path {}: {}", path, e)).into())
+    }
+}
+
+/// Maps the outcome of an HTTP exchange onto the reply body or a `ClientError`.
+pub(crate) fn http_reply(status: reqwest::StatusCode, text: reqwest::Result<String>) -> Result<String> {
+    let text = match text {
+        Ok(t) => t,
+        Err(e) => return Err(ClientError::Network(format!("reading reply failed: {}", e)).into())
+    };
+
+    if !status.is_success() {
+        return Err(ClientError::Http { status: status.as_u16(), body: body_snippet(&text) }.into())
+    }
+
+    Ok(text)
+}
+
+impl Transport for HttpTransport {
+    fn post(&self, path: &str, body: String) -> Result<String> {
+        let mut b = self
+            .client
+            .post(self.url(path)?)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body);
+
+        if let Some(auth_token) = &self.auth_token {
+            b = b.bearer_auth(auth_token);
+        }
+
+        let res = match b.send() {
+            Ok(r) => r,
+            Err(e) => return Err(ClientError::Network(format!("{}", e)).into())
+        };
+
+        let status = res.status();
+        http_reply(status, res.text())
+    }
+}
+
+#[test]
+fn test_http_transport_url() {
+    let transport = HttpTransport::new("https://mpc.bitverse.zone/api/".to_string(), None);
+    let url = transport.url("bitverse/wallet/v1/private/mpc/ecdsa/sign/first").unwrap();
+    assert_eq!(url.as_str(), "https://mpc.bitverse.zone/api/bitverse/wallet/v1/private/mpc/ecdsa/sign/first");
+}
@@ -0,0 +1,73 @@
+//! Version of the library and of its C ABI.
+
+use std::os::raw::c_char;
+
+use crate::utilities::{catch_panic, success_to_c_string};
+
+pub const MPC_CLIENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Bumped whenever an exported function is removed, changes its arguments or
+/// changes the shape of its `CResult.result`. New functions and new optional
+/// fields in a result do not bump it.
+pub const MPC_CLIENT_ABI_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub struct ClientVersion {
+    version: String,
+    abi_version: u32,
+}
+
+/// The crate version and `MPC_CLIENT_ABI_VERSION`, as a `CResult`.
+#[no_mangle]
+pub extern "C" fn mpc_client_version() -> *mut c_char {
+    catch_panic(|| {
+        success_to_c_string(ClientVersion {
+            version: MPC_CLIENT_VERSION.to_string(),
+            abi_version: MPC_CLIENT_ABI_VERSION,
+        })
+    })
+}
+
+/// `MPC_CLIENT_ABI_VERSION` of the loaded library, to compare with the one
+/// in the header the app was built against. Nothing to free.
+#[no_mangle]
+pub extern "C" fn mpc_client_abi_version() -> u32 {
+    MPC_CLIENT_ABI_VERSION
+}
+
+#[test]
+fn test_header_up_to_date() {
+    let crate_dir = env!("CARGO_MANIFEST_DIR");
+    let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir)).unwrap();
+    let mut generated = Vec::new();
+    cbindgen::generate_with_config(crate_dir, config).unwrap().write(&mut generated);
+
+    let committed = std::fs::read(format!("{}/include/bw_mpc_client.h", crate_dir)).unwrap();
+    assert!(generated == committed, "include/bw_mpc_client.h is stale, run: cbindgen -c cbindgen.toml -o include/bw_mpc_client.h");
+}
+
+#[test]
+fn test_result_schema_covers_header() {
+    let crate_dir = env!("CARGO_MANIFEST_DIR");
+    let header = std::fs::read_to_string(format!("{}/include/bw_mpc_client.h", crate_dir)).unwrap();
+    let schema: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(format!("{}/docs/ffi-results.schema.json", crate_dir)).unwrap()
+    ).unwrap();
+
+    // functions that do not return a `CResult`
+    let plain = ["free_char", "free_client_shim", "hello", "mpc_client_abi_version"];
+
+    // declarations start at the beginning of a line, continued arguments are indented
+    let functions: Vec<&str> = header.lines()
+        .filter(|l| l.contains('(') && !l.starts_with(' ') && !l.starts_with('#') && !l.starts_with("/*"))
+        .map(|l| l[..l.find('(').unwrap()].rsplit(|c| c == ' ' || c == '*').next().unwrap())
+        .collect();
+    assert!(functions.contains(&"mpc_client_version"));
+
+    for f in functions {
+        if plain.contains(&f) {
+            continue
+        }
+        assert!(schema["$defs"].get(f).is_some(), "no result schema for {}", f);
+    }
+}